@@ -0,0 +1,118 @@
+use crate::x_bogus::rc4;
+use rand::{thread_rng, Rng};
+use sm3::{Digest, Sm3};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// 加密 User-Agent 时使用的 base64 字符表
+const UA_CHARACTER: &[u8; 64] = b"ckdp1h4ZKsUB80/Mfvw36XIgR25+WQAlEi7NLboqYTOPuzmFjJnryx9HVGDaStCe";
+// 输出结果使用的 base64 字符表
+const CHARACTER: &[u8; 64] = b"Dkdpgh2ZmsQB80/MfvV36XI1R45-WUAlEixNLwoqYTOPuzKFjJnry79HbGcaStCe";
+// 加密 User-Agent 使用的 RC4 密钥
+const UA_KEY: [u8; 3] = [0, 1, 14];
+// 参与摘要计算的固定后缀
+const END_STRING: &str = "cus";
+// 浏览器环境信息：窗口与屏幕尺寸、色深、平台
+const BROWSER: &str = "1536|742|1536|864|0|0|0|0|1536|864|1536|864|1536|742|24|24|Win32";
+
+/// 计算请求参数的 a_bogus 签名
+pub fn sign(query: &str, user_agent: &str) -> String {
+    let start_time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|t| t.as_millis() as u64)
+        .unwrap_or_default();
+    let mut rng = thread_rng();
+    let end_time = start_time + rng.gen_range(4..=8);
+    let random = [(); 3].map(|_| rng.gen_range(0..10000));
+    sign_at(query, user_agent, "GET", start_time, end_time, random)
+}
+
+/// 按指定的时间（毫秒）和随机数计算 a_bogus 签名
+pub fn sign_at(
+    query: &str,
+    user_agent: &str,
+    method: &str,
+    start_time: u64,
+    end_time: u64,
+    random: [u16; 3],
+) -> String {
+    // 三组随机数，每组按不同的掩码拆成 4 字节
+    let mut data = Vec::new();
+    for (random, [b, c, d, e]) in
+        random
+            .into_iter()
+            .zip([[1, 2, 5, 40], [1, 0, 0, 0], [1, 0, 5, 0]])
+    {
+        let (low, high) = ((random & 255) as u8, (random >> 8) as u8);
+        data.extend([low & 170 | b, low & 85 | c, high & 170 | d, high & 85 | e]);
+    }
+
+    // 请求参数、请求方法各取两次 SM3，User-Agent 经 RC4 加密、base64 编码后取 SM3
+    let params = double_sm3(format!("{}{}", query, END_STRING).as_bytes());
+    let method = double_sm3(format!("{}{}", method, END_STRING).as_bytes());
+    let ua = user_agent
+        .encode_utf16()
+        .map(|c| c as u8)
+        .collect::<Vec<u8>>();
+    let ua: [u8; 32] = Sm3::digest(encode(&rc4(&UA_KEY, &ua), UA_CHARACTER)).into();
+
+    let byte = |value: u64, shift: u32| (value >> shift) as u8;
+    #[rustfmt::skip]
+    let mut payload = vec![
+        44, byte(end_time, 24), 0, 0, 0, 0, 24, params[21], method[21], 0, ua[23], byte(end_time, 16),
+        0, 0, 0, 1, 0, 239, params[22], method[22], ua[24], byte(end_time, 8), 0, 0,
+        0, 0, byte(end_time, 0), 0, 0, 14, byte(start_time, 24), byte(start_time, 16), 0, byte(start_time, 8),
+        byte(start_time, 0), 3, byte(end_time, 32), 1, byte(start_time, 32), 1, BROWSER.len() as u8, 0,
+        0, 0,
+    ];
+    let check = payload.iter().fold(0, |acc, v| acc ^ v);
+    payload.extend(BROWSER.as_bytes());
+    payload.push(check);
+
+    data.extend(rc4(b"y", &payload));
+    encode(&data, CHARACTER)
+}
+
+fn double_sm3(data: &[u8]) -> [u8; 32] {
+    Sm3::digest(Sm3::digest(data)).into()
+}
+
+/// 使用自定义字符表的 base64 编码，不足时补 `=`
+fn encode(data: &[u8], character: &[u8; 64]) -> String {
+    let mut result = String::new();
+    for chunk in data.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | u32::from(b) << (16 - 8 * i));
+        for (i, shift) in [18, 12, 6, 0].into_iter().enumerate() {
+            if i <= chunk.len() {
+                result.push(character[((n >> shift) & 63) as usize] as char);
+            } else {
+                result.push('=');
+            }
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::sign_at;
+    use crate::http::DEFAULT_USER_AGENT;
+
+    #[test]
+    fn matches_reference() {
+        // 由 Python 参考实现在相同的时间和随机数下生成
+        let expected = "E7mhBdugDifBkVWh56KLfY3q6fgVYkQI0SVkMD2fVBdVqL39HMOk9exoIBGvXFEjwG/-Ieujy4hbT3ohrQ2y0Hwf9W0L/25ksDSkKl5Q5xSSs1X9eghgJ04qmkt5SMx2RvB-rOXmqhZHKRbp09oHmhK4bIOwu3GMbE==";
+        let query = "aid=6383&sec_user_id=MS4wLjABAAAA&count=10";
+        let actual = sign_at(
+            query,
+            DEFAULT_USER_AGENT,
+            "GET",
+            1700000000123,
+            1700000000129,
+            [1234, 5678, 9999],
+        );
+        assert_eq!(actual, expected);
+    }
+}
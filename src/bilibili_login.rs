@@ -0,0 +1,227 @@
+use crate::error::VpmError;
+use crate::http;
+use crate::monitor::send_json;
+use crate::state::{self, BilibiliLogin};
+use crate::throttle;
+use qrcode::render::unicode;
+use qrcode::QrCode;
+use rand::thread_rng;
+use reqwest::header::{COOKIE, REFERER};
+use rsa::pkcs8::DecodePublicKey;
+use rsa::{Oaep, RsaPublicKey};
+use serde_json::Value;
+use sha2::Sha256;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+const QRCODE_GENERATE_API: &str =
+    "https://passport.bilibili.com/x/passport-login/web/qrcode/generate";
+const QRCODE_POLL_API: &str = "https://passport.bilibili.com/x/passport-login/web/qrcode/poll";
+const COOKIE_INFO_API: &str = "https://passport.bilibili.com/x/passport-login/web/cookie/info";
+const COOKIE_REFRESH_API: &str =
+    "https://passport.bilibili.com/x/passport-login/web/cookie/refresh";
+const CONFIRM_REFRESH_API: &str =
+    "https://passport.bilibili.com/x/passport-login/web/confirm/refresh";
+const CORRESPOND_URL: &str = "https://www.bilibili.com/correspond/1/";
+const LOGIN_REFERER: &str = "https://www.bilibili.com/";
+// 生成 correspondPath 使用的公钥
+const CORRESPOND_PUBLIC_KEY: &str = "-----BEGIN PUBLIC KEY-----
+MIGfMA0GCSqGSIb3DQEBAQUAA4GNADCBiQKBgQDLgd2OAkcGVtoE3ThUREbio0Eg
+Uc/prcajMKXvkCKFCWhJYJcLkcM2DKKcSeFpD/j6Boy538YXnR6VhcuUJOhH2x71
+nzPjfdTcqMz7djHum0qSZA0AyCBDABUqCrfNgCiJ00Ra7GmRj+YCK1NJEuewlb40
+JNrRuoEUXpabUzGB8QIDAQAB
+-----END PUBLIC KEY-----";
+// interval of polling the QR code status
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+// the QR code expires after 180 seconds
+const QRCODE_TIMEOUT: Duration = Duration::from_secs(180);
+
+/// 扫码登录，在终端中显示二维码并等待手机确认
+pub fn login() -> Result<BilibiliLogin, VpmError> {
+    let json = check_code(send_json(
+        http::client("bilibili")?
+            .get(QRCODE_GENERATE_API)
+            .header(REFERER, LOGIN_REFERER),
+    )?)?;
+    let url = json["data"]["url"]
+        .as_str()
+        .ok_or_else(|| VpmError::Parse("missing field `data.url`".to_string()))?;
+    let qrcode_key = json["data"]["qrcode_key"]
+        .as_str()
+        .ok_or_else(|| VpmError::Parse("missing field `data.qrcode_key`".to_string()))?;
+
+    let qrcode = QrCode::new(url.as_bytes()).map_err(|err| VpmError::Parse(err.to_string()))?;
+    // 终端通常为深色背景，反转颜色以便扫描
+    let qrcode = qrcode
+        .render::<unicode::Dense1x2>()
+        .dark_color(unicode::Dense1x2::Light)
+        .light_color(unicode::Dense1x2::Dark)
+        .build();
+    println!("{}", qrcode);
+    println!("Scan the QR code with the Bilibili app, or open: {}", url);
+
+    let started = Instant::now();
+    let mut scanned = false;
+    while started.elapsed() < QRCODE_TIMEOUT {
+        std::thread::sleep(POLL_INTERVAL);
+        let response = http::client("bilibili")?
+            .get(QRCODE_POLL_API)
+            .query(&[("qrcode_key", qrcode_key)])
+            .header(REFERER, LOGIN_REFERER)
+            .send()?
+            .error_for_status()?;
+        let cookies = http::set_cookies(&response);
+        let json = check_code(response.json::<Value>()?)?;
+        let data = &json["data"];
+        match data["code"].as_i64().unwrap_or(-1) {
+            0 => {
+                let refresh_token = data["refresh_token"]
+                    .as_str()
+                    .ok_or_else(|| VpmError::Parse("missing field `refresh_token`".to_string()))?;
+                return Ok(BilibiliLogin {
+                    cookies: http::merge_cookies("", &cookies),
+                    refresh_token: refresh_token.to_string(),
+                });
+            }
+            // 86101 未扫码
+            86101 => {}
+            // 86090 已扫码未确认
+            86090 => {
+                if !scanned {
+                    println!("Scanned, confirm the login on your phone.");
+                    scanned = true;
+                }
+            }
+            // 86038 二维码已失效
+            code => {
+                return Err(VpmError::Api {
+                    platform: "bilibili",
+                    code,
+                    message: data["message"].as_str().unwrap_or_default().to_string(),
+                })
+            }
+        }
+    }
+    Err(VpmError::Api {
+        platform: "bilibili",
+        code: 86038,
+        message: "QR code expired".to_string(),
+    })
+}
+
+/// 检查登录保存的 cookies 是否需要刷新，需要时刷新并保存到状态文件，返回是否进行了刷新
+pub fn refresh_if_needed() -> Result<bool, VpmError> {
+    let Some(login) = state::read_current().and_then(|state| state.bilibili_login) else {
+        return Ok(false);
+    };
+    let csrf = http::cookie_value(&login.cookies, "bili_jct").unwrap_or_default();
+
+    let info = throttle::request("bilibili", || {
+        check_code(send_json(
+            http::client("bilibili")?
+                .get(COOKIE_INFO_API)
+                .query(&[("csrf", csrf)])
+                .header(COOKIE, &login.cookies),
+        )?)
+    })?;
+    if !info["data"]["refresh"].as_bool().unwrap_or(false) {
+        return Ok(false);
+    }
+
+    let timestamp = info["data"]["timestamp"].as_u64().unwrap_or_else(|| {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|t| t.as_millis() as u64)
+            .unwrap_or_default()
+    });
+    let refreshed = refresh(&login, timestamp)?;
+    state::update_current(|state| state.bilibili_login = Some(refreshed)).map_err(VpmError::Io)?;
+    Ok(true)
+}
+
+/// 按官方流程刷新 cookies：取得 refresh_csrf，换取新的 cookies 和 refresh_token，再确认刷新使旧的失效
+fn refresh(login: &BilibiliLogin, timestamp: u64) -> Result<BilibiliLogin, VpmError> {
+    let csrf = http::cookie_value(&login.cookies, "bili_jct").unwrap_or_default();
+
+    let correspond_path = correspond_path(timestamp)?;
+    let html = throttle::request("bilibili", || {
+        Ok(http::client("bilibili")?
+            .get(format!("{}{}", CORRESPOND_URL, correspond_path))
+            .header(COOKIE, &login.cookies)
+            .send()?
+            .error_for_status()?
+            .text()?)
+    })?;
+    let refresh_csrf = html
+        .split_once(r#"<div id="1-name">"#)
+        .and_then(|(_, rest)| rest.split_once("</div>"))
+        .map(|(refresh_csrf, _)| refresh_csrf.trim().to_string())
+        .filter(|refresh_csrf| !refresh_csrf.is_empty())
+        .ok_or_else(|| VpmError::Parse("missing refresh_csrf".to_string()))?;
+
+    let (cookies, json) = throttle::request("bilibili", || {
+        let response = http::client("bilibili")?
+            .post(COOKIE_REFRESH_API)
+            .form(&[
+                ("csrf", csrf),
+                ("refresh_csrf", &refresh_csrf),
+                ("source", "main_web"),
+                ("refresh_token", &login.refresh_token),
+            ])
+            .header(COOKIE, &login.cookies)
+            .send()?
+            .error_for_status()?;
+        let cookies = http::set_cookies(&response);
+        Ok((cookies, check_code(response.json::<Value>()?)?))
+    })?;
+    let refreshed = BilibiliLogin {
+        cookies: http::merge_cookies(&login.cookies, &cookies),
+        refresh_token: json["data"]["refresh_token"]
+            .as_str()
+            .ok_or_else(|| VpmError::Parse("missing field `refresh_token`".to_string()))?
+            .to_string(),
+    };
+
+    // 使用新的 cookies 确认刷新，旧的 refresh_token 随之失效
+    let new_csrf = http::cookie_value(&refreshed.cookies, "bili_jct").unwrap_or_default();
+    throttle::request("bilibili", || {
+        check_code(send_json(
+            http::client("bilibili")?
+                .post(CONFIRM_REFRESH_API)
+                .form(&[("csrf", new_csrf), ("refresh_token", &login.refresh_token)])
+                .header(COOKIE, &refreshed.cookies),
+        )?)
+    })?;
+    Ok(refreshed)
+}
+
+/// 用公钥以 RSA-OAEP 加密 `refresh_<毫秒时间戳>`，结果为十六进制字符串
+fn correspond_path(timestamp: u64) -> Result<String, VpmError> {
+    let key = RsaPublicKey::from_public_key_pem(CORRESPOND_PUBLIC_KEY)
+        .map_err(|err| VpmError::Parse(err.to_string()))?;
+    let encrypted = key
+        .encrypt(
+            &mut thread_rng(),
+            Oaep::new::<Sha256>(),
+            format!("refresh_{}", timestamp).as_bytes(),
+        )
+        .map_err(|err| VpmError::Parse(err.to_string()))?;
+    Ok(encrypted.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// 检查通行证接口返回的 code
+fn check_code(json: Value) -> Result<Value, VpmError> {
+    match json["code"].as_i64() {
+        Some(0) => Ok(json),
+        // -101 账号未登录
+        Some(-101) => Err(VpmError::AuthExpired {
+            platform: "bilibili",
+            message: json["message"].as_str().unwrap_or_default().to_string(),
+        }),
+        Some(code) => Err(VpmError::Api {
+            platform: "bilibili",
+            code,
+            message: json["message"].as_str().unwrap_or_default().to_string(),
+        }),
+        None => Err(VpmError::Parse("missing field `code`".to_string())),
+    }
+}
@@ -0,0 +1,92 @@
+use clap::{Parser, Subcommand, ValueEnum};
+
+/// Video Platform Monitor
+#[derive(Parser, Debug)]
+#[command(name = "vpm", version, about)]
+pub struct Cli {
+    /// 配置文件路径，默认为当前目录下的 config.toml
+    #[arg(short, long, global = true, value_name = "PATH")]
+    pub config: Option<String>,
+
+    /// 状态文件路径，默认为配置文件所在目录下的 state.toml
+    #[arg(long, global = true, value_name = "PATH")]
+    pub state: Option<String>,
+
+    /// 运行结束后不等待回车退出
+    #[arg(long, global = true)]
+    pub no_pause: bool,
+
+    /// 只输出新视频和错误信息
+    #[arg(short, long, global = true)]
+    pub quiet: bool,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// 检查一次所有资产的视频更新（默认）
+    Check,
+    /// 持续运行，按各资产的检查间隔定时检查视频更新
+    Watch {
+        /// 全局检查间隔（秒），覆盖配置文件中的 watch.interval
+        #[arg(short, long)]
+        interval: Option<u64>,
+    },
+    /// 添加一个资产
+    Add {
+        /// 作者主页链接
+        url: String,
+        /// 资产名称
+        #[arg(short, long)]
+        name: Option<String>,
+    },
+    /// 按名称或链接移除资产
+    Remove {
+        /// 资产名称或链接
+        target: String,
+    },
+    /// 列出所有资产
+    List,
+    /// 查看资产的偏移量历史
+    History {
+        /// 资产名称或链接，不填则显示全部
+        target: Option<String>,
+    },
+    /// 扫码登录平台账号，cookies 保存在状态文件中
+    Login {
+        /// 平台名称
+        platform: LoginPlatform,
+    },
+    /// cookies 相关操作
+    Cookies {
+        #[command(subcommand)]
+        command: CookiesCommand,
+    },
+    /// 配置文件相关操作
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigCommand {
+    /// 检查配置文件是否合法
+    Validate,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum CookiesCommand {
+    /// 从 Netscape 格式的 cookies.txt、Firefox 的 cookies.sqlite 或 Firefox 配置目录导入 cookies
+    Import {
+        /// 文件或目录路径
+        path: String,
+    },
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum LoginPlatform {
+    Bilibili,
+}
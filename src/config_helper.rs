@@ -1,205 +1,369 @@
-use chrono::{Datelike, NaiveDate};
+use crate::error::VpmError;
+use crate::platform;
+use crate::pool::Limits;
+use crate::state::Offset;
+use base64::Engine;
 use serde::{Deserialize, Serialize};
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::io::ErrorKind;
 use toml::de::Error;
-use toml::value::{Date, Datetime};
+use toml_edit::{ArrayOfTables, DocumentMut, Item, Table};
 
-#[derive(Deserialize, Serialize, Clone, Debug)]
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
 pub struct Config {
     pub assets: Option<Vec<Asset>>,
     pub cookies: Option<CookieJar>,
+    pub watch: Option<WatchConfig>,
+    pub download: Option<DownloadConfig>,
+    pub live: Option<LiveConfig>,
+    pub rate_limit: Option<RateLimitConfig>,
+    pub http: Option<HttpConfig>,
+    // 各平台自己的设置，键为平台名称，由平台解析
+    pub platforms: Option<HashMap<String, toml::Table>>,
 }
 
-#[derive(Deserialize, Serialize, Clone, Debug)]
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
 pub struct Asset {
     pub name: Option<String>,
     pub link: String,
-    // 最大长度为 3
-    // 分别记录 今日、上次、上上次 的偏移量
+    // 资产的唯一标识，状态文件以此保存偏移量，默认为 `平台:作者 id`
+    pub id: Option<String>,
+    // 检查间隔（秒），优先级高于平台和全局设置
+    pub interval: Option<u64>,
+    // 是否自动下载新视频
+    pub download: Option<bool>,
+    // 下载的最高清晰度，如 `1080P+`、`4K`，默认为账号可用的最高清晰度
+    pub quality: Option<String>,
+    // 优先使用的视频编码：avc / hevc / av1
+    pub codec: Option<String>,
+    // 合并音视频后的封装格式：mp4 / mkv，默认为 mp4
+    pub container: Option<String>,
+    // 是否检查直播状态
+    pub live: Option<bool>,
+    // 开播时是否自动录制直播
+    pub record_live: Option<bool>,
+    // 旧版本保存在配置中的运行时状态，仅用于迁移到状态文件
+    #[serde(skip_serializing)]
+    pub living: Option<bool>,
+    #[serde(skip_serializing)]
     pub offsets: Option<Vec<Offset>>,
 }
 impl Asset {
-    pub fn get_id(&self) -> String {
+    /// 资产的唯一标识：配置中的 id，否则为链接解析出的 `平台:作者 id`，修改名称或链接的写法不会改变
+    ///
+    /// 链接无法解析时返回错误，不能退回到其他标识，否则会读写另一份状态
+    pub fn get_id(&self) -> Result<String, VpmError> {
+        match &self.id {
+            Some(id) => Ok(id.clone()),
+            None => platform::resolve(&self.link).map(|author| author.key()),
+        }
+    }
+
+    /// 链接指向的作者，`平台:作者 id`
+    pub fn author_key(&self) -> Option<String> {
+        platform::resolve(&self.link)
+            .ok()
+            .map(|author| author.key())
+    }
+
+    /// 旧版本的标识，修改名称或链接后会改变
+    pub fn legacy_id(&self) -> String {
         // name + link base64
         let no_name = "NoN".to_string();
         let name = self.name.as_ref().unwrap_or(&no_name);
         let link = &self.link;
         let id = format!("{}{}", name, link);
-        return base64::encode(id);
+        base64::engine::general_purpose::STANDARD.encode(id)
+    }
+
+    /// 名称或链接与 target 相同
+    pub fn matches(&self, target: &str) -> bool {
+        self.name.as_deref() == Some(target) || self.link == target
     }
 }
-pub trait AssetVec {
-    fn get_by_id(&mut self, id: &str) -> Option<&mut Asset>;
+/// watch 模式设置
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+pub struct WatchConfig {
+    // 全局检查间隔（秒）
+    pub interval: Option<u64>,
+    // 每次检查额外随机延迟的最大秒数，用于避开风控
+    pub jitter: Option<u64>,
+    // 各平台的检查间隔（秒），键为平台名称
+    pub platforms: Option<HashMap<String, u64>>,
+    // 回溯到上次检查位置时最多获取的页数
+    pub max_pages: Option<u32>,
+    // 同时检查的资产数量
+    pub concurrency: Option<usize>,
+    // 同一平台同时检查的资产数量
+    pub platform_concurrency: Option<usize>,
 }
-impl AssetVec for Vec<Asset> {
-    fn get_by_id(&mut self, id: &str) -> Option<&mut Asset> {
-        for asset in self {
-            if asset.get_id().as_str() == id {
-                return Some(asset);
-            }
-        }
-        return None;
+
+// default watch interval in seconds
+const DEFAULT_WATCH_INTERVAL: u64 = 600;
+// default watch jitter in seconds
+const DEFAULT_WATCH_JITTER: u64 = 30;
+// default max pages fetched per check
+const DEFAULT_MAX_PAGES: u32 = 5;
+// default number of assets checked at the same time
+const DEFAULT_CONCURRENCY: usize = 8;
+// default number of assets of one platform checked at the same time
+const DEFAULT_PLATFORM_CONCURRENCY: usize = 2;
+
+impl Config {
+    /// 通过不同的链接指向同一作者的资产，返回 (重复资产的下标, 最先出现的资产的下标)
+    ///
+    /// 按链接指向的作者判断，不受资产 id 影响
+    pub fn duplicate_assets(&self) -> Vec<(usize, usize)> {
+        duplicates(self.assets.iter().flatten().map(|asset| asset.author_key()))
     }
-}
 
-#[derive(Deserialize, Serialize, Clone, Debug)]
-pub struct Offset {
-    pub date: Datetime,
-    pub mark: String,
-}
-pub trait OffsetVec {
-    fn get_newest_offset(&self, not_today: bool) -> Option<&Offset>;
-    fn get_oldest_offset(&self, not_today: bool) -> Option<&Offset>;
-    fn remove_newest_offset(&mut self);
-    fn remove_oldest_offset(&mut self);
-}
-impl OffsetVec for Vec<Offset> {
-    fn get_newest_offset(&self, not_today: bool) -> Option<&Offset> {
-        if self.is_empty() {
-            return None;
-        }
+    /// 配置了相同 id 的资产，它们会共用状态文件中的同一份状态，返回值同 [`Config::duplicate_assets`]
+    pub fn duplicate_ids(&self) -> Vec<(usize, usize)> {
+        duplicates(self.assets.iter().flatten().map(|asset| asset.id.clone()))
+    }
 
-        // 对比日期，返回最新的日期
-        let mut newest: Option<&Offset> = None;
-        for offset in self {
-            if not_today {
-                let today = chrono::Local::now().date_naive();
-                let today = naive_date_to_date(today);
-                if offset.date.date.unwrap() == today {
-                    continue;
-                }
-            }
-            if newest.is_none() {
-                newest = Some(offset);
-            } else if offset.date > newest.unwrap().date {
-                newest = Some(offset);
-            }
-        }
-        return newest;
+    /// 计算资产的检查间隔：资产 > 平台 > 全局 > 平台默认值
+    pub fn get_interval(&self, asset: &Asset, platform: &str) -> u64 {
+        let watch = self.watch.as_ref();
+        asset
+            .interval
+            .or_else(|| {
+                watch
+                    .and_then(|w| w.platforms.as_ref())
+                    .and_then(|p| p.get(platform).copied())
+            })
+            .or_else(|| watch.and_then(|w| w.interval))
+            .or_else(|| platform::get(platform).and_then(|p| p.defaults().interval))
+            .unwrap_or(DEFAULT_WATCH_INTERVAL)
     }
 
-    fn get_oldest_offset(&self, not_today: bool) -> Option<&Offset> {
-        if self.is_empty() {
-            return None;
-        }
+    pub fn get_jitter(&self) -> u64 {
+        self.watch
+            .as_ref()
+            .and_then(|w| w.jitter)
+            .unwrap_or(DEFAULT_WATCH_JITTER)
+    }
 
-        // 对比日期，返回最旧的日期
-        let mut oldest: Option<&Offset> = None;
-        for offset in self {
-            if not_today {
-                let today = chrono::Local::now().date_naive();
-                let today = naive_date_to_date(today);
-                if offset.date.date.unwrap() == today {
-                    continue;
-                }
-            }
-            if oldest.is_none() {
-                oldest = Some(offset);
-            } else if offset.date < oldest.unwrap().date {
-                oldest = Some(offset);
-            }
-        }
-        return oldest;
+    pub fn get_max_pages(&self) -> u32 {
+        self.watch
+            .as_ref()
+            .and_then(|w| w.max_pages)
+            .unwrap_or(DEFAULT_MAX_PAGES)
     }
 
-    fn remove_newest_offset(&mut self) {
-        // 对比日期，删除最新的日期
-        let date = {
-            let newest = self.get_newest_offset(false);
-            match newest {
-                None => return,
-                Some(offset) => offset.date.date,
-            }
-        };
-        self.retain(|offset| offset.date.date != date);
+    /// 并发检查的数量限制：全局和单个平台
+    pub fn get_concurrency(&self) -> Limits {
+        let watch = self.watch.as_ref();
+        Limits {
+            global: watch
+                .and_then(|w| w.concurrency)
+                .unwrap_or(DEFAULT_CONCURRENCY),
+            platform: watch
+                .and_then(|w| w.platform_concurrency)
+                .unwrap_or(DEFAULT_PLATFORM_CONCURRENCY),
+        }
     }
+}
 
-    fn remove_oldest_offset(&mut self) {
-        // 对比日期，删除最旧的日期
-        let date = {
-            let oldest = self.get_oldest_offset(false);
-            match oldest {
-                None => return,
-                Some(offset) => offset.date.date,
-            }
+/// 与前面的键相同的下标，返回 (重复的下标, 最先出现的下标)，键为 None 的不参与比较
+fn duplicates(keys: impl Iterator<Item = Option<String>>) -> Vec<(usize, usize)> {
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    let mut duplicates = Vec::new();
+    for (index, key) in keys.enumerate() {
+        let Some(key) = key else {
+            continue;
         };
-        self.retain(|offset| offset.date.date != date);
+        match seen.entry(key) {
+            Entry::Occupied(first) => duplicates.push((index, *first.get())),
+            Entry::Vacant(entry) => {
+                entry.insert(index);
+            }
+        }
     }
+    duplicates
 }
 
-#[derive(Deserialize, Serialize, Clone, Debug)]
-pub struct CookieJar {
-    pub bilibili: Option<String>,
-    pub kuaishou: Option<String>,
-    pub ixigua: Option<String>,
-    pub douyin: Option<String>,
+/// 请求限流与重试设置
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+pub struct RateLimitConfig {
+    // 每个平台每分钟的请求数
+    pub rate: Option<f64>,
+    // 允许连续发送的请求数
+    pub burst: Option<u32>,
+    // 各平台每分钟的请求数，键为平台名称
+    pub platforms: Option<HashMap<String, f64>>,
+    // 网络错误等暂时性错误的重试次数
+    pub retries: Option<u32>,
+    // 触发风控后暂停请求该平台的秒数
+    pub cooldown: Option<u64>,
 }
 
-// default config file path
-const DEFAULT_CONFIG_PATH: &str = "config.toml";
+/// HTTP 客户端设置
+#[derive(Deserialize, Serialize, Clone, Debug, Default, PartialEq)]
+pub struct HttpConfig {
+    // 连接超时（秒）
+    pub connect_timeout: Option<u64>,
+    // 读取超时（秒），不作用于视频下载和直播录制
+    pub timeout: Option<u64>,
+    // 代理地址，支持 http://、https://、socks5:// 和 socks5h://
+    pub proxy: Option<String>,
+    pub user_agent: Option<String>,
+    // 附加到每个请求的请求头
+    pub headers: Option<HashMap<String, String>>,
+    // 各平台的设置，键为平台名称
+    pub platforms: Option<HashMap<String, PlatformHttpConfig>>,
+}
 
-pub fn read_config(path: Option<&str>) -> Result<Config, Error> {
-    let config_path = path.unwrap_or(DEFAULT_CONFIG_PATH);
-    touch_config(config_path);
-    let config = std::fs::read_to_string(config_path).unwrap();
-    let config: Config = toml::from_str(&config)?;
-    return Ok(config);
+/// 单个平台的 HTTP 设置，优先级高于全局设置
+#[derive(Deserialize, Serialize, Clone, Debug, Default, PartialEq)]
+pub struct PlatformHttpConfig {
+    // 为空字符串时该平台不使用代理
+    pub proxy: Option<String>,
+    pub user_agent: Option<String>,
+    pub headers: Option<HashMap<String, String>>,
 }
 
-pub fn save_config(config: &Config, path: Option<&str>) -> Result<(), std::io::Error> {
-    let config_path = path.unwrap_or(DEFAULT_CONFIG_PATH);
-    touch_config(config_path);
-    let config = toml::to_string(config).unwrap();
-    std::fs::write(config_path, config)
+/// 自动下载设置
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+pub struct DownloadConfig {
+    // 下载目录，默认为 downloads
+    pub dir: Option<String>,
+    // 下载记录文件，默认为下载目录下的 archive.txt
+    pub archive: Option<String>,
+    // ffmpeg 可执行文件路径，用于合并音视频
+    pub ffmpeg: Option<String>,
 }
 
-pub fn touch_config(path: &str) {
-    // 检查文件是否存在，不存在则创建
-    if !std::path::Path::new(path).exists() {
-        std::fs::write(path, "").expect(&format!("Failed to create file: {}", path));
-    }
+/// 直播录制设置
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+pub struct LiveConfig {
+    // 录制目录，默认为 recordings
+    pub dir: Option<String>,
+    // 单个分段的最大大小（MB）
+    pub segment_size: Option<u64>,
+    // 单个分段的最大时长（秒）
+    pub segment_time: Option<u64>,
 }
 
-pub fn update_offset(asset: &mut Asset, offset_mark: &str) {
-    if asset.offsets.is_none() {
-        asset.offsets = Some(vec![]);
+/// 各平台的 cookies，键为平台名称
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+#[serde(transparent)]
+pub struct CookieJar(HashMap<String, String>);
+impl CookieJar {
+    /// 根据平台名称获取 cookies
+    pub fn get(&self, platform: &str) -> Option<&str> {
+        self.0.get(platform).map(String::as_str)
     }
-    let offset = asset.offsets.as_mut().unwrap();
 
-    let today = chrono::Local::now().date_naive();
-    let today = Datetime::from(naive_date_to_date(today));
+    /// 设置平台的 cookies
+    pub fn set(&mut self, platform: &str, cookies: String) {
+        self.0.insert(platform.to_string(), cookies);
+    }
+}
 
-    if let Some(newest_offset) = offset.get_newest_offset(false) {
-        if newest_offset.mark == offset_mark {
-            return;
-        }
+// default config file path
+const DEFAULT_CONFIG_PATH: &str = "config.toml";
 
-        if newest_offset.date == today {
-            offset.remove_newest_offset();
-        }
-        offset.push(Offset {
-            date: today,
-            mark: offset_mark.to_string(),
-        });
-    } else {
-        offset.push(Offset {
-            date: today,
-            mark: offset_mark.to_string(),
-        });
+/// 读取配置文件，文件不存在时返回错误，不会创建文件
+pub fn read_config(path: Option<&str>) -> Result<Config, VpmError> {
+    let config_path = config_path(path);
+    let config = std::fs::read_to_string(config_path).map_err(|err| match err.kind() {
+        ErrorKind::NotFound => VpmError::Io(format!("config file {} not found", config_path)),
+        _ => VpmError::Io(format!("failed to read {}: {}", config_path, err)),
+    })?;
+    toml::from_str(&config)
+        .map_err(|err: Error| VpmError::Config(format!("{}: {}", config_path, err)))
+}
+
+pub fn config_path(path: Option<&str>) -> &str {
+    path.unwrap_or(DEFAULT_CONFIG_PATH)
+}
+
+/// 读取配置文件为可编辑的文档，保留注释和格式
+fn read_document(config_path: &str) -> Result<DocumentMut, String> {
+    match std::fs::read_to_string(config_path) {
+        Ok(config) => config.parse::<DocumentMut>().map_err(|err| err.to_string()),
+        // 添加第一个资产时再创建配置文件
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(DocumentMut::new()),
+        Err(err) => Err(err.to_string()),
     }
+}
 
-    if offset.len() > 3 {
-        offset.remove_oldest_offset();
+/// 向配置文件中追加资产
+pub fn add_asset(path: Option<&str>, name: Option<&str>, link: &str) -> Result<(), String> {
+    let config_path = config_path(path);
+    let mut document = read_document(config_path)?;
+
+    let mut table = Table::new();
+    if let Some(name) = name {
+        table["name"] = toml_edit::value(name);
     }
+    table["link"] = toml_edit::value(link);
+
+    document
+        .entry("assets")
+        .or_insert(Item::ArrayOfTables(ArrayOfTables::new()))
+        .as_array_of_tables_mut()
+        .ok_or("`assets` is not an array of tables")?
+        .push(table);
+
+    std::fs::write(config_path, document.to_string()).map_err(|err| err.to_string())
 }
 
-fn naive_date_to_date(date: NaiveDate) -> Date {
-    Date {
-        year: date.year() as u16,
-        month: date.month() as u8,
-        day: date.day() as u8,
+/// 从配置文件中移除名称或链接与 target 相同的资产，返回移除的数量
+pub fn remove_assets(path: Option<&str>, target: &str) -> Result<usize, String> {
+    let config_path = config_path(path);
+    let mut document = read_document(config_path)?;
+
+    let assets = match document
+        .get_mut("assets")
+        .and_then(|a| a.as_array_of_tables_mut())
+    {
+        Some(assets) => assets,
+        None => return Ok(0),
+    };
+    let count = assets.len();
+    assets.retain(|table| {
+        let name = table.get("name").and_then(|n| n.as_str());
+        let link = table.get("link").and_then(|l| l.as_str());
+        name != Some(target) && link != Some(target)
+    });
+    let removed = count - assets.len();
+
+    if removed > 0 {
+        std::fs::write(config_path, document.to_string()).map_err(|err| err.to_string())?;
     }
+    Ok(removed)
 }
 
-fn date_to_naive_date(date: Date) -> NaiveDate {
-    NaiveDate::from_ymd_opt(date.year as i32, date.month as u32, date.day as u32).unwrap()
+#[cfg(test)]
+mod tests {
+    use super::Config;
+
+    #[test]
+    fn duplicate_assets() {
+        let config: Config = toml::from_str(
+            r#"
+            [[assets]]
+            id = "x"
+            link = "https://space.bilibili.com/1"
+
+            [[assets]]
+            id = "y"
+            link = "https://m.bilibili.com/space/1"
+
+            [[assets]]
+            id = "x"
+            link = "https://space.bilibili.com/2"
+
+            [[assets]]
+            link = "https://example.com/unsupported"
+            "#,
+        )
+        .unwrap();
+        // 不同的 id 不影响按作者判断重复，相同的 id 单独报告
+        assert_eq!(config.duplicate_assets(), [(1, 0)]);
+        assert_eq!(config.duplicate_ids(), [(2, 0)]);
+    }
 }
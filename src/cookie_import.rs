@@ -0,0 +1,138 @@
+use crate::error::VpmError;
+use crate::platform;
+use rusqlite::Connection;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// SQLite 数据库文件的文件头
+const SQLITE_HEADER: &[u8] = b"SQLite format 3\0";
+
+/// 平台名称 -> 该平台的 cookies (名称, 值)
+pub type PlatformCookies = BTreeMap<&'static str, Vec<(String, String)>>;
+
+struct Cookie {
+    host: String,
+    name: String,
+    value: String,
+    // 过期时间（秒），0 为会话 cookie
+    expiry: i64,
+}
+
+/// 从 Netscape 格式的 cookies.txt、Firefox 的 cookies.sqlite 或 Firefox 配置目录中读取各平台的 cookies
+///
+/// 只返回找到未过期 cookies 的平台
+pub fn import(path: &Path) -> Result<PlatformCookies, VpmError> {
+    let path = if path.is_dir() {
+        path.join("cookies.sqlite")
+    } else {
+        path.to_path_buf()
+    };
+    let content =
+        std::fs::read(&path).map_err(|err| VpmError::Io(format!("{}: {}", path.display(), err)))?;
+    let mut cookies = if content.starts_with(SQLITE_HEADER) {
+        read_firefox(&path)?
+    } else {
+        read_netscape(&String::from_utf8_lossy(&content))
+    };
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|t| t.as_secs() as i64)
+        .unwrap_or_default();
+    // 同名 cookie 优先使用适用范围最大的域名
+    cookies.sort_by_key(|cookie| cookie.host.trim_start_matches('.').len());
+
+    let mut platforms = PlatformCookies::new();
+    for cookie in cookies {
+        if cookie.expiry != 0 && cookie.expiry < now {
+            continue;
+        }
+        let host = cookie.host.trim_start_matches('.');
+        let Some(platform) = platform::all().find(|platform| {
+            let domain = platform.cookie_domain();
+            host == domain || host.ends_with(&format!(".{}", domain))
+        }) else {
+            continue;
+        };
+        let pairs = platforms.entry(platform.name()).or_default();
+        if !pairs.iter().any(|(name, _)| *name == cookie.name) {
+            pairs.push((cookie.name, cookie.value));
+        }
+    }
+    Ok(platforms)
+}
+
+/// 解析 Netscape 格式：域名、是否包含子域名、路径、是否仅 HTTPS、过期时间、名称、值，以制表符分隔
+fn read_netscape(content: &str) -> Vec<Cookie> {
+    content
+        .lines()
+        .filter_map(|line| {
+            // HttpOnly 的 cookie 以 `#HttpOnly_` 开头，其余以 `#` 开头的为注释
+            let line = line.strip_prefix("#HttpOnly_").unwrap_or(line);
+            if line.starts_with('#') {
+                return None;
+            }
+            let fields = line
+                .trim_end_matches(['\r', '\n'])
+                .split('\t')
+                .collect::<Vec<_>>();
+            match fields[..] {
+                [host, _, _, _, expiry, name, value] => Some(Cookie {
+                    host: host.to_string(),
+                    name: name.to_string(),
+                    value: value.to_string(),
+                    expiry: expiry.parse().unwrap_or(0),
+                }),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// 读取 Firefox 的 cookies 数据库
+///
+/// Firefox 运行时会锁定数据库，因此先连同 WAL 文件一起复制到临时目录再读取
+fn read_firefox(path: &Path) -> Result<Vec<Cookie>, VpmError> {
+    let temp_path = std::env::temp_dir().join(format!("vpm-cookies-{}.sqlite", std::process::id()));
+    let with_suffix = |path: &Path, suffix: &str| {
+        let mut path = path.as_os_str().to_owned();
+        path.push(suffix);
+        PathBuf::from(path)
+    };
+    let io_error = |err: std::io::Error| VpmError::Io(format!("{}: {}", path.display(), err));
+    std::fs::copy(path, &temp_path).map_err(io_error)?;
+    if with_suffix(path, "-wal").exists() {
+        std::fs::copy(with_suffix(path, "-wal"), with_suffix(&temp_path, "-wal"))
+            .map_err(io_error)?;
+    }
+
+    let cookies = query_firefox(&temp_path)
+        .map_err(|err| VpmError::Parse(format!("{}: {}", path.display(), err)));
+    for suffix in ["", "-wal", "-shm"] {
+        let _ = std::fs::remove_file(with_suffix(&temp_path, suffix));
+    }
+    cookies
+}
+
+fn query_firefox(path: &Path) -> Result<Vec<Cookie>, rusqlite::Error> {
+    let connection = Connection::open(path)?;
+    let mut statement = connection.prepare("SELECT host, name, value, expiry FROM moz_cookies")?;
+    let cookies = statement
+        .query_map([], |row| {
+            let expiry: i64 = row.get(3)?;
+            Ok(Cookie {
+                host: row.get(0)?,
+                name: row.get(1)?,
+                value: row.get(2)?,
+                // 较新版本的 Firefox 以毫秒保存过期时间
+                expiry: if expiry > 100_000_000_000 {
+                    expiry / 1000
+                } else {
+                    expiry
+                },
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(cookies)
+}
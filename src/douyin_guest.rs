@@ -0,0 +1,184 @@
+use crate::error::VpmError;
+use crate::http;
+use crate::state::{self, DouyinGuest};
+use crate::throttle;
+use rand::{thread_rng, Rng};
+use serde_json::json;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const TTWID_REGISTER_API: &str = "https://ttwid.bytedance.com/ttwid/union/register/";
+// msToken 使用的字符
+const MS_TOKEN_CHARACTER: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
+// verifyFp 使用的字符
+const VERIFY_FP_CHARACTER: &[u8] =
+    b"0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
+// 浏览器中 msToken 的长度
+const MS_TOKEN_LENGTH: usize = 107;
+
+// 已缓存的游客身份，首次使用时从状态文件读取
+static GUEST: Mutex<Option<DouyinGuest>> = Mutex::new(None);
+
+/// 发送抖音请求时使用的身份
+pub struct Session {
+    // Cookie 请求头
+    pub cookie: String,
+    // 需要追加到请求参数中的 msToken、verifyFp
+    pub params: Vec<(&'static str, String)>,
+    guest: Option<DouyinGuest>,
+}
+
+impl Session {
+    fn new(cookies: Option<&str>, guest: Option<DouyinGuest>) -> Session {
+        let cookies = cookies.unwrap_or_default().trim().trim_end_matches(';');
+        let mut cookie = Some(cookies.to_string())
+            .filter(|c| !c.is_empty())
+            .into_iter()
+            .collect::<Vec<_>>();
+        let mut params = Vec::new();
+        if let Some(guest) = &guest {
+            // 配置中已有的 cookie 优先
+            for (name, value) in [
+                ("ttwid", &guest.ttwid),
+                ("msToken", &guest.ms_token),
+                ("s_v_web_id", &guest.verify_fp),
+            ] {
+                if http::cookie_value(cookies, name).is_none() {
+                    cookie.push(format!("{}={}", name, value));
+                }
+            }
+            params.push(("msToken", guest.ms_token.clone()));
+            params.push(("verifyFp", guest.verify_fp.clone()));
+            params.push(("fp", guest.verify_fp.clone()));
+        }
+        Session {
+            cookie: cookie.join("; "),
+            params,
+            guest,
+        }
+    }
+}
+
+/// 发送抖音请求
+///
+/// 配置的 cookies 中没有 ttwid 时使用游客身份，游客身份被拒绝（返回空响应）时重新获取一次
+pub fn send<T>(
+    cookies: Option<&str>,
+    send: impl Fn(&Session) -> Result<T, VpmError>,
+) -> Result<T, VpmError> {
+    let logged_in = cookies.is_some_and(|c| http::cookie_value(c, "ttwid").is_some());
+    if logged_in {
+        return send(&Session::new(cookies, None));
+    }
+
+    let session = Session::new(cookies, Some(guest()?));
+    match send(&session) {
+        Err(VpmError::RiskControl { .. }) => {
+            let guest = refresh(session.guest.as_ref())?;
+            send(&Session::new(cookies, Some(guest)))
+        }
+        result => result,
+    }
+}
+
+/// 取得游客身份，没有缓存时从状态文件读取，仍然没有时重新获取
+fn guest() -> Result<DouyinGuest, VpmError> {
+    let mut cached = GUEST.lock().unwrap();
+    if cached.is_none() {
+        *cached = state::read_current().and_then(|state| state.douyin);
+    }
+    if let Some(guest) = cached.as_ref() {
+        return Ok(guest.clone());
+    }
+    let guest = register()?;
+    *cached = Some(guest.clone());
+    Ok(guest)
+}
+
+/// 重新获取被拒绝的游客身份，其他线程已经更新过时直接使用新的身份
+fn refresh(rejected: Option<&DouyinGuest>) -> Result<DouyinGuest, VpmError> {
+    let mut cached = GUEST.lock().unwrap();
+    if let Some(guest) = cached.as_ref().filter(|guest| Some(*guest) != rejected) {
+        return Ok(guest.clone());
+    }
+    let guest = register()?;
+    *cached = Some(guest.clone());
+    Ok(guest)
+}
+
+/// 注册新的游客身份并保存到状态文件
+fn register() -> Result<DouyinGuest, VpmError> {
+    let guest = DouyinGuest {
+        ttwid: register_ttwid()?,
+        ms_token: ms_token(),
+        verify_fp: verify_fp(),
+    };
+    if let Err(err) = state::update_current(|state| state.douyin = Some(guest.clone())) {
+        eprintln!("Failed to save douyin guest cookies: {}", err);
+    }
+    Ok(guest)
+}
+
+/// 从公开的注册接口获取 ttwid，结果在 Set-Cookie 中
+fn register_ttwid() -> Result<String, VpmError> {
+    throttle::request("douyin", || {
+        let response = http::client("douyin")?
+            .post(TTWID_REGISTER_API)
+            .json(&json!({
+                "region": "cn",
+                "aid": 1768,
+                "needFid": false,
+                "service": "www.ixigua.com",
+                "migrate_info": { "ticket": "", "source": "node" },
+                "cbUrlProtocol": "https",
+                "union": true,
+            }))
+            .send()?
+            .error_for_status()?;
+        http::set_cookies(&response)
+            .into_iter()
+            .find(|(name, value)| name == "ttwid" && !value.is_empty())
+            .map(|(_, ttwid)| ttwid)
+            .ok_or_else(|| VpmError::Parse("missing ttwid cookie".to_string()))
+    })
+}
+
+/// 随机生成 msToken
+fn ms_token() -> String {
+    let mut rng = thread_rng();
+    (0..MS_TOKEN_LENGTH)
+        .map(|_| MS_TOKEN_CHARACTER[rng.gen_range(0..MS_TOKEN_CHARACTER.len())] as char)
+        .collect()
+}
+
+/// 按网页脚本的规则生成 verifyFp，同时用作 s_v_web_id
+///
+/// 格式为 `verify_<36 进制毫秒时间戳>_<类似 UUID 的 36 位随机串>`
+fn verify_fp() -> String {
+    let mut millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|t| t.as_millis() as u64)
+        .unwrap_or_default();
+    let mut timestamp = Vec::new();
+    while millis > 0 {
+        timestamp.push(std::char::from_digit((millis % 36) as u32, 36).unwrap());
+        millis /= 36;
+    }
+    let timestamp = timestamp.iter().rev().collect::<String>();
+
+    let mut rng = thread_rng();
+    let random = (0..36)
+        .map(|i| match i {
+            8 | 13 | 18 | 23 => '_',
+            14 => '4',
+            _ => {
+                let mut n = rng.gen_range(0..VERIFY_FP_CHARACTER.len());
+                if i == 19 {
+                    n = n & 3 | 8;
+                }
+                VERIFY_FP_CHARACTER[n] as char
+            }
+        })
+        .collect::<String>();
+    format!("verify_{}_{}", timestamp, random)
+}
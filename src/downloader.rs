@@ -0,0 +1,320 @@
+use crate::config_helper::{Asset, DownloadConfig};
+use crate::error::VpmError;
+use crate::http;
+use crate::monitor::NewestVideo;
+use crate::platform;
+use reqwest::header::{RANGE, REFERER};
+use reqwest::StatusCode;
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+// default download directory
+const DEFAULT_DOWNLOAD_DIR: &str = "downloads";
+// default download archive file name, relative to the download directory
+const DEFAULT_ARCHIVE_NAME: &str = "archive.txt";
+// default ffmpeg executable
+const DEFAULT_FFMPEG: &str = "ffmpeg";
+
+/// 视频需要下载的单个文件
+pub struct MediaFile {
+    pub url: String,
+    // 文件后缀，如 `mp4`、`01.jpg`
+    pub suffix: String,
+    pub referer: Option<String>,
+}
+
+/// 一个视频的下载任务
+pub struct MediaTask {
+    pub files: Vec<MediaFile>,
+    // 下载完成后使用 ffmpeg 合并为指定后缀的文件，如音视频分离的 DASH 流
+    pub merge: Option<String>,
+}
+
+/// 视频的最终下载状态
+pub enum DownloadStatus {
+    Downloaded(Vec<PathBuf>),
+    // 下载完成，但写入下载记录失败
+    Unrecorded(Vec<PathBuf>, std::io::Error),
+    // 已存在于下载记录中
+    Archived,
+    Failed(VpmError),
+}
+
+impl Display for DownloadStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let join = |paths: &[PathBuf]| {
+            paths
+                .iter()
+                .map(|p| p.display().to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        match self {
+            DownloadStatus::Downloaded(paths) => write!(f, "downloaded {}", join(paths)),
+            DownloadStatus::Unrecorded(paths, err) => write!(
+                f,
+                "downloaded {}, but failed to write download archive: {}",
+                join(paths),
+                err
+            ),
+            DownloadStatus::Archived => write!(f, "already downloaded"),
+            DownloadStatus::Failed(err) => write!(f, "download failed: {}", err),
+        }
+    }
+}
+
+pub struct Downloader {
+    dir: PathBuf,
+    ffmpeg: String,
+    archive_path: PathBuf,
+    // 已下载的视频，格式为 `平台 视频id`
+    archive: HashSet<String>,
+}
+
+impl Downloader {
+    pub fn new(config: Option<&DownloadConfig>) -> Downloader {
+        let dir = PathBuf::from(
+            config
+                .and_then(|c| c.dir.as_deref())
+                .unwrap_or(DEFAULT_DOWNLOAD_DIR),
+        );
+        let archive_path = config
+            .and_then(|c| c.archive.as_deref())
+            .map(PathBuf::from)
+            .unwrap_or_else(|| dir.join(DEFAULT_ARCHIVE_NAME));
+
+        let archive = File::open(&archive_path)
+            .map(|file| {
+                BufReader::new(file)
+                    .lines()
+                    .map_while(Result::ok)
+                    .map(|line| line.trim().to_string())
+                    .filter(|line| !line.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let ffmpeg = config
+            .and_then(|c| c.ffmpeg.clone())
+            .unwrap_or(DEFAULT_FFMPEG.to_string());
+
+        Downloader {
+            dir,
+            ffmpeg,
+            archive_path,
+            archive,
+        }
+    }
+
+    /// 下载视频到 `下载目录/资产名称/` 下
+    pub fn download(
+        &mut self,
+        platform: &str,
+        asset: &Asset,
+        video: &NewestVideo,
+        cookies: Option<&str>,
+    ) -> DownloadStatus {
+        let key = format!("{} {}", platform, video.id);
+        if self.archive.contains(&key) {
+            return DownloadStatus::Archived;
+        }
+
+        match self.download_video(platform, asset, video, cookies) {
+            // 写入失败时由调用方随资产的其他输出一起打印
+            Ok(paths) => match self.record(&key) {
+                Ok(()) => DownloadStatus::Downloaded(paths),
+                Err(err) => DownloadStatus::Unrecorded(paths, err),
+            },
+            Err(err) => DownloadStatus::Failed(err),
+        }
+    }
+
+    fn download_video(
+        &self,
+        platform: &str,
+        asset: &Asset,
+        video: &NewestVideo,
+        cookies: Option<&str>,
+    ) -> Result<Vec<PathBuf>, VpmError> {
+        let asset_name = asset.name.as_deref().unwrap_or("NoN");
+        let dir = self.dir.join(sanitize_file_name(asset_name));
+        let stem = file_stem(video);
+
+        let task = resolve_media(platform, asset, video, cookies)?;
+
+        // 合并后的文件已存在，说明此前已下载完成
+        let merged = task
+            .merge
+            .as_ref()
+            .map(|ext| dir.join(format!("{}.{}", stem, ext)));
+        if let Some(merged) = merged.as_ref().filter(|p| p.exists()) {
+            return Ok(vec![merged.clone()]);
+        }
+
+        std::fs::create_dir_all(&dir)
+            .map_err(|err| VpmError::Io(format!("{}: {}", dir.display(), err)))?;
+
+        let mut paths = Vec::new();
+        for file in &task.files {
+            let path = dir.join(format!("{}.{}", stem, file.suffix));
+            fetch_resumable(platform, &file.url, file.referer.as_deref(), &path)?;
+            paths.push(path);
+        }
+
+        match merged {
+            Some(merged) => {
+                self.merge(&paths, &merged)?;
+                for path in &paths {
+                    let _ = std::fs::remove_file(path);
+                }
+                Ok(vec![merged])
+            }
+            None => Ok(paths),
+        }
+    }
+
+    /// 使用 ffmpeg 将多个流无损合并为一个文件
+    fn merge(&self, inputs: &[PathBuf], output: &Path) -> Result<(), VpmError> {
+        let mut command = Command::new(&self.ffmpeg);
+        command.args(["-y", "-loglevel", "error"]);
+        for input in inputs {
+            command.arg("-i").arg(input);
+        }
+        for index in 0..inputs.len() {
+            command.args(["-map", &index.to_string()]);
+        }
+        command.args(["-c", "copy"]).arg(output);
+
+        let result = command
+            .output()
+            .map_err(|err| VpmError::Io(format!("failed to run {}: {}", self.ffmpeg, err)))?;
+        if !result.status.success() {
+            let _ = std::fs::remove_file(output);
+            return Err(VpmError::Io(format!(
+                "ffmpeg exited with {}: {}",
+                result.status,
+                String::from_utf8_lossy(&result.stderr).trim()
+            )));
+        }
+        Ok(())
+    }
+
+    /// 追加下载记录，写入文件失败时本次运行中同样不再重复下载
+    fn record(&mut self, key: &str) -> std::io::Result<()> {
+        self.archive.insert(key.to_string());
+        if let Some(parent) = self.archive_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.archive_path)?;
+        writeln!(file, "{}", key)
+    }
+}
+
+/// 解析视频的实际媒体地址
+fn resolve_media(
+    platform: &str,
+    asset: &Asset,
+    video: &NewestVideo,
+    cookies: Option<&str>,
+) -> Result<MediaTask, VpmError> {
+    let platform =
+        platform::get(platform).ok_or_else(|| VpmError::UnsupportedUrl(video.url.clone()))?;
+    if !platform.capabilities().download {
+        return Err(platform.unsupported("download"));
+    }
+    platform.resolve_media(asset, video, cookies)
+}
+
+/// 资产是否设置了优先使用 H.265 编码
+pub(crate) fn prefer_h265(asset: &Asset) -> Option<bool> {
+    asset
+        .codec
+        .as_deref()
+        .map(|c| matches!(c.trim().to_lowercase().as_str(), "hevc" | "h265"))
+}
+
+/// 断点续传下载文件，下载过程中写入 `*.part`，完成后重命名
+fn fetch_resumable(
+    platform: &str,
+    url: &str,
+    referer: Option<&str>,
+    path: &Path,
+) -> Result<(), VpmError> {
+    if path.exists() {
+        return Ok(());
+    }
+    let io_error = |err: std::io::Error| VpmError::Io(format!("{}: {}", path.display(), err));
+
+    let part_path = PathBuf::from(format!("{}.part", path.display()));
+    let downloaded = std::fs::metadata(&part_path).map(|m| m.len()).unwrap_or(0);
+
+    let mut request = http::stream_client(platform)?
+        .get(url)
+        .header(REFERER, referer.unwrap_or(""));
+    if downloaded > 0 {
+        request = request.header(RANGE, format!("bytes={}-", downloaded));
+    }
+    let mut response = request.send()?;
+
+    let mut file = match response.status() {
+        // 服务器支持断点续传，追加写入
+        StatusCode::PARTIAL_CONTENT => OpenOptions::new()
+            .append(true)
+            .open(&part_path)
+            .map_err(io_error)?,
+        // 已下载完整
+        StatusCode::RANGE_NOT_SATISFIABLE => {
+            return std::fs::rename(&part_path, path).map_err(io_error);
+        }
+        // 不支持断点续传，重新下载
+        status if status.is_success() => File::create(&part_path).map_err(io_error)?,
+        status => {
+            return Err(VpmError::HttpStatus {
+                status: status.as_u16(),
+                url: url.to_string(),
+            })
+        }
+    };
+
+    response.copy_to(&mut file)?;
+    file.flush().map_err(io_error)?;
+    drop(file);
+    std::fs::rename(&part_path, path).map_err(io_error)
+}
+
+/// 文件名：`日期 标题 [视频id]`
+fn file_stem(video: &NewestVideo) -> String {
+    let date = video
+        .date
+        .parse::<i64>()
+        .ok()
+        .and_then(|ms| chrono::DateTime::from_timestamp(ms / 1000, 0))
+        .map(|d| {
+            d.with_timezone(&chrono::Local)
+                .format("%Y-%m-%d")
+                .to_string()
+        })
+        .unwrap_or_default();
+    let title = video.title.chars().take(80).collect::<String>();
+    sanitize_file_name(&format!("{} {} [{}]", date, title, video.id))
+}
+
+/// 替换文件名中不允许出现的字符
+pub(crate) fn sanitize_file_name(name: &str) -> String {
+    let name = name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => ' ',
+            c => c,
+        })
+        .collect::<String>();
+    name.trim().trim_end_matches('.').to_string()
+}
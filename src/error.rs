@@ -0,0 +1,143 @@
+use std::fmt::{Display, Formatter};
+
+/// vpm 运行过程中可能出现的错误
+#[derive(Debug)]
+pub enum VpmError {
+    /// 网络错误（连接失败、超时等）
+    Network(reqwest::Error),
+    /// 服务器返回了非 2xx 状态码
+    HttpStatus { status: u16, url: String },
+    /// 平台风控（如 Bilibili -352/-412）
+    RiskControl {
+        platform: &'static str,
+        code: i64,
+        message: String,
+    },
+    /// 登录态失效或 cookies 过期
+    AuthExpired {
+        platform: &'static str,
+        message: String,
+    },
+    /// 平台返回的其他业务错误码
+    Api {
+        platform: &'static str,
+        code: i64,
+        message: String,
+    },
+    /// 平台处于风控冷却中，暂停请求
+    Cooldown {
+        platform: &'static str,
+        remaining: u64,
+    },
+    /// 服务器返回了空响应
+    EmptyResponse,
+    /// 响应解析失败，通常意味着平台接口结构发生了变化
+    Parse(String),
+    /// 不支持的链接
+    UnsupportedUrl(String),
+    /// 不久前已解析失败过的链接
+    Unresolved { link: String, reason: String },
+    /// 平台不支持该功能，如西瓜视频的直播
+    Unsupported {
+        platform: &'static str,
+        feature: &'static str,
+    },
+    /// 配置项不合法
+    Config(String),
+    /// 本地文件读写失败
+    Io(String),
+}
+
+impl Display for VpmError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VpmError::Network(err) => write!(f, "network error: {}", err),
+            VpmError::HttpStatus { status, url } => {
+                write!(f, "unexpected HTTP status {} from {}", status, url)
+            }
+            VpmError::RiskControl {
+                platform,
+                code,
+                message,
+            } => write!(
+                f,
+                "{} risk control triggered (code {}): {}",
+                platform, code, message
+            ),
+            VpmError::AuthExpired { platform, message } => {
+                write!(f, "{} login expired: {}", platform, message)
+            }
+            VpmError::Api {
+                platform,
+                code,
+                message,
+            } => write!(f, "{} api error (code {}): {}", platform, code, message),
+            VpmError::Cooldown {
+                platform,
+                remaining,
+            } => write!(
+                f,
+                "{} is cooling down after risk control, retry in {}s",
+                platform, remaining
+            ),
+            VpmError::EmptyResponse => write!(f, "empty response body"),
+            VpmError::Parse(message) => write!(f, "failed to parse response: {}", message),
+            VpmError::UnsupportedUrl(url) => write!(f, "unsupported url: {}", url),
+            VpmError::Unsupported { platform, feature } => {
+                write!(f, "{} is not supported on {}", feature, platform)
+            }
+            VpmError::Unresolved { link, reason } => {
+                write!(f, "failed to resolve {}: {}", link, reason)
+            }
+            VpmError::Config(message) => write!(f, "invalid config: {}", message),
+            VpmError::Io(message) => write!(f, "io error: {}", message),
+        }
+    }
+}
+
+impl VpmError {
+    /// 是否为可以重试的暂时性错误
+    pub fn is_transient(&self) -> bool {
+        match self {
+            VpmError::Network(_) | VpmError::EmptyResponse => true,
+            VpmError::HttpStatus { status, .. } => *status == 429 || *status >= 500,
+            _ => false,
+        }
+    }
+}
+
+impl std::error::Error for VpmError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            VpmError::Network(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for VpmError {
+    fn from(err: reqwest::Error) -> Self {
+        if err.is_decode() {
+            return VpmError::Parse(err.to_string());
+        }
+        match err.status() {
+            Some(status) => VpmError::HttpStatus {
+                status: status.as_u16(),
+                url: err.url().map(|u| u.to_string()).unwrap_or_default(),
+            },
+            None => VpmError::Network(err),
+        }
+    }
+}
+
+impl From<serde_json::Error> for VpmError {
+    fn from(err: serde_json::Error) -> Self {
+        VpmError::Parse(err.to_string())
+    }
+}
+
+impl From<url::ParseError> for VpmError {
+    fn from(err: url::ParseError) -> Self {
+        VpmError::UnsupportedUrl(err.to_string())
+    }
+}
@@ -0,0 +1,190 @@
+use crate::config_helper::{HttpConfig, PlatformHttpConfig};
+use crate::error::VpmError;
+use reqwest::blocking::{Client, Response};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, SET_COOKIE, USER_AGENT};
+use reqwest::Proxy;
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::time::Duration;
+
+pub(crate) const DEFAULT_USER_AGENT: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/122.0.0.0 Safari/537.36 Edg/122.0.0.0";
+
+// default connect timeout in seconds
+const DEFAULT_CONNECT_TIMEOUT: u64 = 10;
+// default read timeout in seconds
+const DEFAULT_TIMEOUT: u64 = 30;
+// idle timeout of video downloads and live recordings in seconds
+const STREAM_IDLE_TIMEOUT: u64 = 60;
+
+/// 按平台区分的 HTTP 客户端，同一平台的请求复用连接
+struct Clients {
+    config: HttpConfig,
+    // 接口请求使用的客户端，键为平台名称，空字符串为默认客户端
+    api: HashMap<String, Client>,
+    // 下载视频和录制直播使用的客户端，只限制每次读取的等待时间
+    stream: HashMap<String, Client>,
+}
+
+static CLIENTS: RwLock<Option<Clients>> = RwLock::new(None);
+
+/// 根据配置创建 HTTP 客户端，配置未变化时保留已有的客户端
+pub fn configure(config: Option<&HttpConfig>) -> Result<(), VpmError> {
+    let config = config.cloned().unwrap_or_default();
+    let mut clients = CLIENTS.write().unwrap();
+    if clients.as_ref().is_some_and(|c| c.config == config) {
+        return Ok(());
+    }
+
+    let mut api = HashMap::new();
+    let mut stream = HashMap::new();
+    let platforms = config.platforms.clone().unwrap_or_default();
+    for (platform, platform_config) in
+        std::iter::once((String::new(), PlatformHttpConfig::default())).chain(platforms)
+    {
+        api.insert(
+            platform.clone(),
+            build_client(&config, &platform_config, false)?,
+        );
+        stream.insert(platform, build_client(&config, &platform_config, true)?);
+    }
+    *clients = Some(Clients {
+        config,
+        api,
+        stream,
+    });
+    Ok(())
+}
+
+fn build_client(
+    config: &HttpConfig,
+    platform: &PlatformHttpConfig,
+    streaming: bool,
+) -> Result<Client, VpmError> {
+    let invalid = |message: String| VpmError::Config(format!("http: {}", message));
+
+    // 平台设置覆盖全局设置
+    let user_agent = platform
+        .user_agent
+        .as_deref()
+        .or(config.user_agent.as_deref())
+        .unwrap_or(DEFAULT_USER_AGENT);
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        USER_AGENT,
+        HeaderValue::from_str(user_agent).map_err(|err| invalid(err.to_string()))?,
+    );
+    for (name, value) in config
+        .headers
+        .iter()
+        .flatten()
+        .chain(platform.headers.iter().flatten())
+    {
+        let name = HeaderName::from_bytes(name.as_bytes())
+            .map_err(|err| invalid(format!("{}: {}", name, err)))?;
+        let value =
+            HeaderValue::from_str(value).map_err(|err| invalid(format!("{}: {}", name, err)))?;
+        headers.insert(name, value);
+    }
+
+    let connect_timeout = config.connect_timeout.unwrap_or(DEFAULT_CONNECT_TIMEOUT);
+    let timeout = config.timeout.unwrap_or(DEFAULT_TIMEOUT);
+    // 阻塞客户端的 timeout 分别作用于等待响应和每次读取响应体：接口请求一次读取完整的响应体，
+    // 视频文件和直播流逐块读取，没有固定的结束时间，超时只在连接停滞时触发，之后重新连接或下次续传
+    let timeout = match streaming {
+        true => STREAM_IDLE_TIMEOUT.max(timeout),
+        false => timeout,
+    };
+    let mut builder = Client::builder()
+        .default_headers(headers)
+        .connect_timeout(Duration::from_secs(connect_timeout))
+        .timeout(Duration::from_secs(timeout));
+
+    // 平台的 proxy 为空字符串时不使用全局代理
+    match platform.proxy.as_deref().or(config.proxy.as_deref()) {
+        Some("") | None => {}
+        Some(proxy) => {
+            let proxy =
+                Proxy::all(proxy).map_err(|err| invalid(format!("proxy {}: {}", proxy, err)))?;
+            builder = builder.proxy(proxy);
+        }
+    }
+    Ok(builder.build()?)
+}
+
+fn get_client(platform: &str, streaming: bool) -> Result<Client, VpmError> {
+    if CLIENTS.read().unwrap().is_none() {
+        configure(None)?;
+    }
+    let clients = CLIENTS.read().unwrap();
+    let clients = clients.as_ref().expect("http clients are configured");
+    let clients = if streaming {
+        &clients.stream
+    } else {
+        &clients.api
+    };
+    Ok(clients
+        .get(platform)
+        .or_else(|| clients.get(""))
+        .expect("default http client exists")
+        .clone())
+}
+
+/// 平台接口请求使用的客户端
+pub fn client(platform: &str) -> Result<Client, VpmError> {
+    get_client(platform, false)
+}
+
+/// 下载视频文件和录制直播使用的客户端
+pub fn stream_client(platform: &str) -> Result<Client, VpmError> {
+    get_client(platform, true)
+}
+
+/// 平台请求使用的 User-Agent，用于需要参与签名的场景
+pub fn user_agent(platform: &str) -> String {
+    let clients = CLIENTS.read().unwrap();
+    let config = clients.as_ref().map(|c| &c.config);
+    config
+        .and_then(|c| c.platforms.as_ref())
+        .and_then(|p| p.get(platform))
+        .and_then(|p| p.user_agent.clone())
+        .or_else(|| config.and_then(|c| c.user_agent.clone()))
+        .unwrap_or_else(|| DEFAULT_USER_AGENT.to_string())
+}
+
+/// 从 Cookie 请求头格式的字符串中读取指定 cookie 的值，值为空时视为不存在
+pub fn cookie_value<'a>(cookies: &'a str, name: &str) -> Option<&'a str> {
+    cookies
+        .split(';')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| key.trim() == name)
+        .map(|(_, value)| value.trim())
+        .filter(|value| !value.is_empty())
+}
+
+/// 读取响应中 Set-Cookie 设置的 (名称, 值)
+pub fn set_cookies(response: &Response) -> Vec<(String, String)> {
+    response
+        .headers()
+        .get_all(SET_COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .filter_map(|value| value.split(';').next()?.split_once('='))
+        .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+        .collect()
+}
+
+/// 用新的 cookie 替换或追加到 Cookie 请求头格式的字符串中
+pub fn merge_cookies(cookies: &str, updates: &[(String, String)]) -> String {
+    let mut merged = cookies
+        .split(';')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+        .filter(|(name, _)| !updates.iter().any(|(update, _)| update == name))
+        .collect::<Vec<_>>();
+    merged.extend(updates.iter().cloned());
+    merged
+        .iter()
+        .map(|(name, value)| format!("{}={}", name, value))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
@@ -0,0 +1,292 @@
+use crate::config_helper::LiveConfig;
+use crate::downloader::sanitize_file_name;
+use crate::error::VpmError;
+use crate::http;
+use crate::platform;
+use reqwest::header::REFERER;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use url::Url;
+
+// default live recording directory
+const DEFAULT_LIVE_DIR: &str = "recordings";
+// default segment size in MB
+const DEFAULT_SEGMENT_SIZE: u64 = 2048;
+// default segment duration in seconds
+const DEFAULT_SEGMENT_TIME: u64 = 3600;
+
+/// 直播间状态
+pub struct LiveStatus {
+    pub living: bool,
+    pub title: String,
+    pub room_url: String,
+    pub(crate) room_id: String,
+    // 平台返回的原始数据，用于解析直播流地址
+    pub(crate) raw: Value,
+}
+
+/// 直播流地址
+pub(crate) struct LiveStream {
+    pub(crate) url: String,
+    pub(crate) referer: String,
+}
+
+/// 获取作者的直播状态
+pub fn get_live_status(link: &str, cookies: Option<&str>) -> Result<LiveStatus, VpmError> {
+    let author = platform::resolve(link)?;
+    if !author.platform.capabilities().live {
+        return Err(author.platform.unsupported("live"));
+    }
+    author.platform.live_status(&author.id, cookies)
+}
+
+fn get_live_stream(
+    platform: &str,
+    status: &LiveStatus,
+    cookies: Option<&str>,
+) -> Result<LiveStream, VpmError> {
+    platform::get(platform)
+        .ok_or_else(|| VpmError::UnsupportedUrl(status.room_url.clone()))?
+        .live_stream(status, cookies)
+}
+
+// ================================================================================================
+// Recorder
+// ================================================================================================
+
+// 正在录制的直播，键为资产 id
+static RECORDINGS: OnceLock<Mutex<HashMap<String, JoinHandle<()>>>> = OnceLock::new();
+// 停止所有录制
+static STOP_RECORDING: AtomicBool = AtomicBool::new(false);
+
+fn recordings() -> &'static Mutex<HashMap<String, JoinHandle<()>>> {
+    RECORDINGS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// 直播录制任务
+pub struct LiveRecording {
+    pub key: String,
+    pub platform: String,
+    pub link: String,
+    pub name: String,
+    pub cookies: Option<String>,
+}
+
+/// 在后台线程中录制直播，直到直播结束；同一资产同时只会有一个录制任务
+pub fn start_recording(recording: LiveRecording, config: Option<&LiveConfig>) -> bool {
+    let mut recordings = recordings().lock().unwrap();
+    recordings.retain(|_, handle| !handle.is_finished());
+    if recordings.contains_key(&recording.key) {
+        return false;
+    }
+
+    let dir = PathBuf::from(
+        config
+            .and_then(|c| c.dir.as_deref())
+            .unwrap_or(DEFAULT_LIVE_DIR),
+    )
+    .join(sanitize_file_name(&recording.name));
+    let segment_size = config
+        .and_then(|c| c.segment_size)
+        .unwrap_or(DEFAULT_SEGMENT_SIZE)
+        * 1024
+        * 1024;
+    let segment_time = Duration::from_secs(
+        config
+            .and_then(|c| c.segment_time)
+            .unwrap_or(DEFAULT_SEGMENT_TIME),
+    );
+
+    let key = recording.key.clone();
+    let handle = std::thread::spawn(move || {
+        if let Err(err) = record(&recording, &dir, segment_size, segment_time) {
+            eprintln!("[{}] live recording failed: {}", recording.name, err);
+        }
+    });
+    recordings.insert(key, handle);
+    true
+}
+
+/// 通知所有录制任务在当前分段结束后停止
+pub fn stop_recordings() {
+    STOP_RECORDING.store(true, Ordering::SeqCst);
+}
+
+/// 等待所有录制任务结束
+pub fn wait_recordings() {
+    let handles = recordings()
+        .lock()
+        .unwrap()
+        .drain()
+        .map(|(_, handle)| handle)
+        .collect::<Vec<_>>();
+    for handle in handles {
+        let _ = handle.join();
+    }
+}
+
+/// 当前正在录制的数量
+pub fn active_recordings() -> usize {
+    let mut recordings = recordings().lock().unwrap();
+    recordings.retain(|_, handle| !handle.is_finished());
+    recordings.len()
+}
+
+/// 按分段录制直播，每个分段重新获取直播流地址，直到直播结束或收到停止信号
+fn record(
+    recording: &LiveRecording,
+    dir: &Path,
+    segment_size: u64,
+    segment_time: Duration,
+) -> Result<(), VpmError> {
+    std::fs::create_dir_all(dir)
+        .map_err(|err| VpmError::Io(format!("{}: {}", dir.display(), err)))?;
+    let cookies = recording.cookies.as_deref();
+
+    while !STOP_RECORDING.load(Ordering::SeqCst) {
+        let status = get_live_status(&recording.link, cookies)?;
+        if !status.living {
+            break;
+        }
+        let stream = get_live_stream(&recording.platform, &status, cookies)?;
+
+        let is_hls = stream
+            .url
+            .split('?')
+            .next()
+            .unwrap_or("")
+            .ends_with(".m3u8");
+        let path = dir.join(format!(
+            "{} {}.{}",
+            chrono::Local::now().format("%Y-%m-%d %H-%M-%S"),
+            sanitize_file_name(&status.title.chars().take(80).collect::<String>()),
+            if is_hls { "ts" } else { "flv" }
+        ));
+        println!("[{}] recording live to {}", recording.name, path.display());
+
+        let written = if is_hls {
+            record_hls_segment(
+                &recording.platform,
+                &stream,
+                &path,
+                segment_size,
+                segment_time,
+            )?
+        } else {
+            record_flv_segment(
+                &recording.platform,
+                &stream,
+                &path,
+                segment_size,
+                segment_time,
+            )?
+        };
+        if written == 0 {
+            let _ = std::fs::remove_file(&path);
+            // 未获取到数据，稍后重试
+            std::thread::sleep(Duration::from_secs(5));
+        }
+    }
+
+    println!("[{}] live recording finished", recording.name);
+    Ok(())
+}
+
+/// 录制一个 FLV 分段，达到大小或时长上限、直播流断开或收到停止信号时返回
+fn record_flv_segment(
+    platform: &str,
+    stream: &LiveStream,
+    path: &Path,
+    segment_size: u64,
+    segment_time: Duration,
+) -> Result<u64, VpmError> {
+    let io_error = |err: std::io::Error| VpmError::Io(format!("{}: {}", path.display(), err));
+    let mut response = http::stream_client(platform)?
+        .get(&stream.url)
+        .header(REFERER, &stream.referer)
+        .send()?
+        .error_for_status()?;
+    let mut file = File::create(path).map_err(io_error)?;
+
+    let started = Instant::now();
+    let mut written = 0u64;
+    let mut buffer = [0u8; 64 * 1024];
+    while !STOP_RECORDING.load(Ordering::SeqCst)
+        && written < segment_size
+        && started.elapsed() < segment_time
+    {
+        let size = match response.read(&mut buffer) {
+            Ok(0) | Err(_) => break,
+            Ok(size) => size,
+        };
+        file.write_all(&buffer[..size]).map_err(io_error)?;
+        written += size as u64;
+    }
+    file.flush().map_err(io_error)?;
+    Ok(written)
+}
+
+/// 录制一个 HLS 分段：轮询播放列表并依次追加新的 ts 切片
+fn record_hls_segment(
+    platform: &str,
+    stream: &LiveStream,
+    path: &Path,
+    segment_size: u64,
+    segment_time: Duration,
+) -> Result<u64, VpmError> {
+    let io_error = |err: std::io::Error| VpmError::Io(format!("{}: {}", path.display(), err));
+    let client = http::stream_client(platform)?;
+    let playlist_url = Url::parse(&stream.url)?;
+    let mut file = File::create(path).map_err(io_error)?;
+
+    let started = Instant::now();
+    let mut written = 0u64;
+    let mut seen = std::collections::HashSet::new();
+    while !STOP_RECORDING.load(Ordering::SeqCst)
+        && written < segment_size
+        && started.elapsed() < segment_time
+    {
+        let playlist = client
+            .get(playlist_url.as_str())
+            .header(REFERER, &stream.referer)
+            .send()
+            .and_then(|r| r.error_for_status())
+            .and_then(|r| r.text());
+        let playlist = match playlist {
+            Ok(playlist) => playlist,
+            Err(_) => break,
+        };
+
+        let mut fetched = false;
+        for line in playlist.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') || !seen.insert(line.to_string()) {
+                continue;
+            }
+            let segment_url = playlist_url.join(line)?;
+            let mut response = client
+                .get(segment_url)
+                .header(REFERER, &stream.referer)
+                .send()?
+                .error_for_status()?;
+            written += response.copy_to(&mut file)?;
+            fetched = true;
+        }
+
+        // 播放列表结束，说明直播已结束
+        if playlist.contains("#EXT-X-ENDLIST") {
+            break;
+        }
+        if !fetched {
+            std::thread::sleep(Duration::from_secs(2));
+        }
+    }
+    file.flush().map_err(io_error)?;
+    Ok(written)
+}
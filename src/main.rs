@@ -1,91 +1,817 @@
-use crate::config_helper::{update_offset, AssetVec, OffsetVec};
+use crate::cli::{Cli, Command, ConfigCommand, CookiesCommand, LoginPlatform};
+use crate::config_helper::{Asset, Config};
+use crate::downloader::{DownloadStatus, Downloader};
+use crate::live::LiveRecording;
+use crate::state::{AssetUpdate, OffsetVec, State};
+use clap::Parser;
+use rand::{thread_rng, Rng};
+use std::collections::{HashMap, HashSet};
 use std::io;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
+mod a_bogus;
+mod bilibili_login;
+mod cli;
 mod config_helper;
+mod cookie_import;
+mod douyin_guest;
+mod downloader;
+mod error;
+mod http;
+mod live;
 mod monitor;
+mod platform;
+mod pool;
+mod state;
+mod throttle;
+mod x_bogus;
+#[cfg(all(test, feature = "quickjs"))]
 mod x_bogus_js;
 
-fn main() {
-    // config path
-    let config_path = None;
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let config_path = cli.config.as_deref();
+    let state_path = cli
+        .state
+        .map(PathBuf::from)
+        .unwrap_or_else(|| state::state_path(config_helper::config_path(config_path)));
+    let state_path = state_path.as_path();
+    state::set_path(state_path);
+    let scripts_dir = platform::scripts_dir(config_helper::config_path(config_path));
+    for problem in platform::load_scripts(&scripts_dir) {
+        eprintln!("Failed to load monitor script {}", problem);
+    }
+
+    let result = match cli.command.unwrap_or(Command::Check) {
+        Command::Check => {
+            let result = check(config_path, state_path, cli.quiet);
+            if !cli.no_pause {
+                // 等待结束
+                println!("Press Enter to exit...");
+                io::stdin().read_line(&mut String::new()).unwrap();
+            }
+            result
+        }
+        Command::Watch { interval } => watch(config_path, state_path, cli.quiet, interval),
+        Command::Add { url, name } => add(config_path, url, name),
+        Command::Remove { target } => remove(config_path, &target),
+        Command::List => list(config_path),
+        Command::History { target } => history(config_path, state_path, target.as_deref()),
+        Command::Login { platform } => login(config_path, state_path, platform),
+        Command::Cookies {
+            command: CookiesCommand::Import { path },
+        } => import_cookies(state_path, &path),
+        Command::Config {
+            command: ConfigCommand::Validate,
+        } => validate(config_path),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn load_config(config_path: Option<&str>) -> Result<Config, String> {
+    config_helper::read_config(config_path).map_err(|err| err.to_string())
+}
+
+/// 应用限流、平台和 HTTP 设置
+///
+/// 迁移状态时可能需要解析短链接，须在读取状态之前调用
+fn configure(config: &Config) -> Result<(), String> {
+    throttle::configure(config.rate_limit.as_ref());
+    platform::configure(config.platforms.as_ref()).map_err(|err| err.to_string())?;
+    http::configure(config.http.as_ref()).map_err(|err| err.to_string())
+}
+
+/// 读取状态文件，并迁移旧版本的状态
+fn load_state(state_path: &Path, config: &Config) -> Result<State, String> {
+    let mut state = state::read_state(state_path)?;
+    state.remember_authors();
+    if state.migrate(config) {
+        state::save_state(&state, state_path)
+            .map_err(|err| format!("failed to save state: {}", err))?;
+        println!("Migrated asset state in {}", state_path.display());
+    }
+    Ok(state)
+}
+
+/// 把一轮检查中各资产的状态修改一次写入状态文件
+fn save_updates(state_path: &Path, updates: Vec<(&Asset, AssetUpdate)>) -> Result<(), String> {
+    if updates.iter().all(|(_, update)| update.is_empty()) {
+        return Ok(());
+    }
+    state::update(state_path, |state| {
+        for (asset, update) in updates {
+            if let Ok(asset_state) = state.get_mut(asset) {
+                update.apply(asset_state);
+            }
+        }
+    })
+}
+
+// interval of checking whether the login cookies need refreshing in watch mode
+const LOGIN_CHECK_INTERVAL: Duration = Duration::from_secs(12 * 3600);
+
+/// 需要时刷新扫码登录保存的 cookies
+fn refresh_login(quiet: bool) {
+    match bilibili_login::refresh_if_needed() {
+        Ok(true) if !quiet => println!("Refreshed bilibili cookies"),
+        Ok(_) => {}
+        Err(err) => eprintln!("Failed to refresh bilibili cookies: {}", err),
+    }
+}
+
+/// 使用状态文件中导入或扫码登录保存的 cookies 代替配置文件中的 cookies
+fn apply_cookies(config: &mut Config) {
+    let Some(state) = state::read_current() else {
+        return;
+    };
+    let jar = config.cookies.get_or_insert_with(Default::default);
+    for (platform, cookies) in state.cookies {
+        jar.set(&platform, cookies);
+    }
+    if let Some(login) = state.bilibili_login {
+        jar.set("bilibili", login.cookies);
+    }
+}
+
+/// 检查一次所有资产的视频更新
+fn check(config_path: Option<&str>, state_path: &Path, quiet: bool) -> Result<(), String> {
     // 获取设置
-    let config = config_helper::read_config(config_path).unwrap();
+    let mut config = load_config(config_path)?;
+    // 迁移状态时可能需要解析短链接
+    configure(&config)?;
+    let state = load_state(state_path, &config)?;
+    refresh_login(quiet);
+    apply_cookies(&mut config);
+    for duplicate in skip_duplicates(&mut config) {
+        eprintln!("{}", duplicate);
+    }
 
     // 判断配置
-    let _config = config.clone();
-    if _config.assets.is_none() || _config.assets.unwrap().is_empty() {
-        eprintln!("No assets found in config file.");
-        // 等待结束
-        println!("Press Enter to exit...");
-        io::stdin().read_line(&mut String::new()).unwrap();
-        return;
+    let assets = match &config.assets {
+        Some(assets) if !assets.is_empty() => assets,
+        _ => return Err("No assets found in config file.".to_string()),
+    };
+    let mut failed = Vec::new();
+    let mut updates = Vec::new();
+
+    // 并发检查资产，按配置顺序输出
+    let platforms = assets.iter().map(platform_of).collect::<Vec<_>>();
+    pool::run_ordered(
+        assets,
+        &platforms,
+        config.get_concurrency(),
+        |asset| check_asset(&config, &state, asset, quiet),
+        |index, (success, output, update)| {
+            output.print();
+            let asset = &assets[index];
+            if !success {
+                failed.push(asset.name.clone().unwrap_or(asset.link.clone()));
+            }
+            updates.push((asset, update));
+        },
+    );
+    let saved = save_updates(state_path, updates);
+
+    // 等待直播录制结束
+    let recordings = live::active_recordings();
+    if recordings > 0 {
+        println!("Waiting for {} live recording(s) to finish...", recordings);
+        live::wait_recordings();
     }
-    let mut _config = config.clone();
 
-    // 遍历资产配置
-    for asset in &config.assets.unwrap() {
-        // 输出资产名称
-        println!(
-            "[{}]({})'s new videos",
-            asset.name.as_ref().unwrap_or(&"NoN".to_string()),
-            asset.link
-        );
+    saved?;
+    if !failed.is_empty() {
+        return Err(format!(
+            "{} asset(s) failed: {}",
+            failed.len(),
+            failed.join(", ")
+        ));
+    }
+    Ok(())
+}
 
-        // 处理偏移量
-        let show_offset = match &asset.offsets {
-            None => None,
-            Some(_offsets) => _offsets.get_oldest_offset(true).map(|o| o.mark.as_str()),
-        };
-        let is_new_offset = match &asset.offsets {
-            None => None,
-            Some(_offsets) => _offsets.get_newest_offset(true).map(|o| o.mark.as_str()),
+/// 持续运行，按各资产的检查间隔进行检查，收到 SIGINT/SIGTERM 后退出
+fn watch(
+    config_path: Option<&str>,
+    state_path: &Path,
+    quiet: bool,
+    interval: Option<u64>,
+) -> Result<(), String> {
+    let running = Arc::new(AtomicBool::new(true));
+    let _running = running.clone();
+    ctrlc::set_handler(move || _running.store(false, Ordering::SeqCst))
+        .map_err(|err| format!("failed to set signal handler: {}", err))?;
+
+    let mut rng = thread_rng();
+    // 资产链接 -> 下一次检查的时间
+    let mut schedule: HashMap<String, Instant> = HashMap::new();
+    // 上次检查登录 cookies 是否需要刷新的时间
+    let mut login_checked: Option<Instant> = None;
+    // 已提示过的重复资产
+    let mut duplicates: HashSet<String> = HashSet::new();
+
+    while running.load(Ordering::SeqCst) {
+        // 每轮重新读取设置，以便 add/remove 等修改及时生效
+        let loaded = load_config(config_path).and_then(|config| {
+            configure(&config)?;
+            load_state(state_path, &config).map(|state| (config, state))
+        });
+        let (mut config, state) = match loaded {
+            Ok(loaded) => loaded,
+            Err(err) => {
+                eprintln!("Error: {}", err);
+                sleep_until(Instant::now() + Duration::from_secs(60), &running);
+                continue;
+            }
         };
+        if let Some(interval) = interval {
+            config.watch.get_or_insert_with(Default::default).interval = Some(interval);
+        }
+        if login_checked.is_none_or(|checked| checked.elapsed() >= LOGIN_CHECK_INTERVAL) {
+            refresh_login(quiet);
+            login_checked = Some(Instant::now());
+        }
+        apply_cookies(&mut config);
+        for duplicate in skip_duplicates(&mut config) {
+            if duplicates.insert(duplicate.clone()) {
+                eprintln!("{}", duplicate);
+            }
+        }
+        let jitter = config.get_jitter();
+        let assets = config.assets.clone().unwrap_or_default();
 
-        // 获取最新视频
-        match monitor::get_newest_video(asset.link.as_str(), config.cookies.clone(), show_offset, is_new_offset) {
-            Ok((videos, next_offset)) => {
-                if videos.is_empty() {
-                    println!("No new videos found.");
-                } else {
-                    for video in videos {
-                        // parse timestamp ms to date
-                        let date = format_date(&video.date);
-                        println!(
+        // 移除已被删除的资产
+        schedule.retain(|link, _| assets.iter().any(|asset| &asset.link == link));
+
+        // 新资产的首次检查在 jitter 范围内随机错开
+        let due = assets
+            .into_iter()
+            .filter(|asset| {
+                let due = *schedule.entry(asset.link.clone()).or_insert_with(|| {
+                    Instant::now() + Duration::from_secs(rng.gen_range(0..=jitter))
+                });
+                due <= Instant::now()
+            })
+            .collect::<Vec<_>>();
+
+        // 并发检查到期的资产，收到退出信号后不再开始新的检查
+        let mut updates = Vec::new();
+        let platforms = due.iter().map(platform_of).collect::<Vec<_>>();
+        pool::run_ordered(
+            &due,
+            &platforms,
+            config.get_concurrency(),
+            |asset| {
+                running
+                    .load(Ordering::SeqCst)
+                    .then(|| check_asset(&config, &state, asset, quiet))
+            },
+            |index, result| {
+                let Some((_, output, update)) = result else {
+                    return;
+                };
+                output.print();
+
+                let asset = &due[index];
+                updates.push((asset, update));
+                let delay =
+                    config.get_interval(asset, &platforms[index]) + rng.gen_range(0..=jitter);
+                schedule.insert(
+                    asset.link.clone(),
+                    Instant::now() + Duration::from_secs(delay),
+                );
+            },
+        );
+
+        if let Err(err) = save_updates(state_path, updates) {
+            eprintln!("Error: {}", err);
+        }
+
+        // 等待下一个需要检查的资产
+        let next = schedule
+            .values()
+            .min()
+            .copied()
+            .unwrap_or_else(|| Instant::now() + Duration::from_secs(60));
+        sleep_until(next, &running);
+    }
+
+    if !quiet {
+        println!("Shutting down.");
+    }
+    live::stop_recordings();
+    live::wait_recordings();
+    Ok(())
+}
+
+/// 跳过指向同一作者或配置了相同 id 的重复资产，只检查最先出现的一个，返回提示信息
+fn skip_duplicates(config: &mut Config) -> Vec<String> {
+    let mut duplicates = config
+        .duplicate_assets()
+        .into_iter()
+        .map(|(index, first)| (index, first, "author"))
+        .collect::<Vec<_>>();
+    for (index, first) in config.duplicate_ids() {
+        if !duplicates.iter().any(|(duplicate, ..)| *duplicate == index) {
+            duplicates.push((index, first, "id"));
+        }
+    }
+    let Some(assets) = &mut config.assets else {
+        return Vec::new();
+    };
+    let messages = duplicates
+        .iter()
+        .map(|(index, first, what)| {
+            format!(
+                "Skipped {}: same {} as {}",
+                assets[*index].link, what, assets[*first].link
+            )
+        })
+        .collect();
+    let mut index = 0;
+    assets.retain(|_| {
+        index += 1;
+        !duplicates
+            .iter()
+            .any(|(duplicate, ..)| *duplicate == index - 1)
+    });
+    messages
+}
+
+/// 休眠到指定时间，收到退出信号时提前返回
+fn sleep_until(deadline: Instant, running: &AtomicBool) {
+    while running.load(Ordering::SeqCst) {
+        let now = Instant::now();
+        if now >= deadline {
+            return;
+        }
+        std::thread::sleep((deadline - now).min(Duration::from_millis(500)));
+    }
+}
+
+/// 资产所属的平台，用于限制同一平台的并发数，无法解析的链接为空字符串
+///
+/// 每轮检查前为所有资产调用一次，解析失败的链接在一段时间内不会再次请求
+fn platform_of(asset: &Asset) -> String {
+    monitor::get_platform(&asset.link)
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// 单个资产的检查输出
+///
+/// 并发检查时先缓存起来，检查结束后再按配置顺序打印，避免不同资产的输出交错
+#[derive(Default)]
+struct Output {
+    // (是否输出到 stderr, 内容)
+    lines: Vec<(bool, String)>,
+}
+
+impl Output {
+    fn out(&mut self, line: impl Into<String>) {
+        self.lines.push((false, line.into()));
+    }
+
+    fn err(&mut self, line: impl Into<String>) {
+        self.lines.push((true, line.into()));
+    }
+
+    fn print(&self) {
+        for (is_err, line) in &self.lines {
+            if *is_err {
+                eprintln!("{}", line);
+            } else {
+                println!("{}", line);
+            }
+        }
+    }
+}
+
+/// 检查单个资产，返回是否成功、需要输出的内容及对资产状态的修改
+fn check_asset(
+    config: &Config,
+    state: &State,
+    asset: &Asset,
+    quiet: bool,
+) -> (bool, Output, AssetUpdate) {
+    let mut output = Output::default();
+    let mut update = AssetUpdate::default();
+    let asset_name = asset.name.clone().unwrap_or("NoN".to_string());
+    // 输出资产名称
+    if !quiet {
+        output.out(format!("[{}]({})'s new videos", asset_name, asset.link));
+    }
+
+    // 无法确定资产的标识时不读写状态，避免把所有视频当作新视频
+    if let Err(err) = asset.get_id() {
+        output.err(format!("[{}] Error: {}", asset_name, err));
+        return (false, output, update);
+    }
+
+    // 处理偏移量，只用于回溯范围
+    let asset_state = state.get(asset);
+    let offset = asset_state
+        .and_then(|s| s.offsets.get_oldest_offset(true))
+        .map(|o| o.mark.as_str());
+
+    // 获取最新视频
+    let mut success = match monitor::get_newest_video(
+        asset.link.as_str(),
+        config.cookies.clone(),
+        offset,
+        config.get_max_pages(),
+    ) {
+        Ok((mut videos, next_offset)) => {
+            // 根据已见过的视频 id 判断是否为新视频
+            for video in &mut videos {
+                video.is_new = asset_state.is_none_or(|s| s.is_new(&video.id, &video.date));
+            }
+
+            if videos.is_empty() {
+                if !quiet {
+                    output.out("No new videos found.");
+                }
+            } else {
+                for video in &videos {
+                    // parse timestamp ms to date
+                    let date = format_date(&video.date).unwrap_or_else(|| video.date.clone());
+                    // Shorts 等特殊类型在标题前标注
+                    let title = match video.kind.label() {
+                        Some(label) => format!("[{}] {}", label, video.title),
+                        None => video.title.clone(),
+                    };
+                    if quiet {
+                        if video.is_new {
+                            output.out(format!(
+                                "[{}] {} | {} | {}",
+                                asset_name, video.url, date, title
+                            ));
+                        }
+                    } else {
+                        output.out(format!(
                             "{} {} | {} | {}",
                             if video.is_new { "+" } else { "-" },
                             video.url,
                             date,
-                            video.title
-                        );
+                            title
+                        ));
                     }
-
-                    // 更新偏移量
-                    let a = _config.assets.as_mut().unwrap();
-                    let a = a.get_by_id(asset.get_id().as_str()).unwrap();
-                    update_offset(a, next_offset.as_str());
                 }
+
+                // 下载新视频
+                let failed = if asset.download == Some(true) {
+                    download_videos(config, asset, &asset_name, &videos, quiet, &mut output)
+                } else {
+                    Vec::new()
+                };
+
+                // 更新偏移量，下载失败的视频不记录，下次检查时仍为新视频并重新下载
+                update.offset = Some(next_offset);
+                update.seen = videos
+                    .into_iter()
+                    .map(|v| v.id)
+                    .filter(|id| !failed.contains(id))
+                    .collect();
             }
+            true
+        }
+        Err(err) => {
+            output.err(format!("[{}] Error: {}", asset_name, err));
+            false
+        }
+    };
+
+    // 检查直播状态
+    if asset.live == Some(true) || asset.record_live == Some(true) {
+        let was_living = asset_state.is_some_and(|s| s.living);
+        success &= check_live(
+            config,
+            asset,
+            &asset_name,
+            was_living,
+            quiet,
+            &mut output,
+            &mut update,
+        );
+    }
+
+    // 换行
+    if !quiet {
+        output.out("");
+    }
+    (success, output, update)
+}
+
+/// 检查资产的直播状态，输出开播/下播事件，按需开始录制
+fn check_live(
+    config: &Config,
+    asset: &Asset,
+    asset_name: &str,
+    was_living: bool,
+    quiet: bool,
+    output: &mut Output,
+    update: &mut AssetUpdate,
+) -> bool {
+    let platform = monitor::get_platform(&asset.link).unwrap_or_default();
+    let cookies = config.cookies.as_ref().and_then(|c| c.get(platform));
+
+    let status = match live::get_live_status(&asset.link, cookies) {
+        Ok(status) => status,
+        Err(err) => {
+            output.err(format!("[{}] Live check error: {}", asset_name, err));
+            return false;
+        }
+    };
+
+    if status.living && !was_living {
+        output.out(format!(
+            "[{}] went live: {} | {}",
+            asset_name, status.room_url, status.title
+        ));
+    } else if !status.living && was_living {
+        output.out(format!("[{}] live ended", asset_name));
+    } else if status.living && !quiet {
+        output.out(format!(
+            "[{}] is live: {} | {}",
+            asset_name, status.room_url, status.title
+        ));
+    }
+
+    if status.living != was_living {
+        update.living = Some(status.living);
+    }
+
+    if status.living && asset.record_live == Some(true) {
+        let key = match asset.get_id() {
+            Ok(key) => key,
             Err(err) => {
-                eprintln!("Error: {}", err.to_string());
+                output.err(format!("[{}] Error: {}", asset_name, err));
+                return false;
             }
+        };
+        let recording = LiveRecording {
+            key,
+            platform: platform.to_string(),
+            link: asset.link.clone(),
+            name: asset_name.to_string(),
+            cookies: cookies.map(|c| c.to_string()),
+        };
+        live::start_recording(recording, config.live.as_ref());
+    }
+    true
+}
+
+/// 下载资产的新视频并输出每个视频的下载状态，返回下载失败的视频 id
+fn download_videos(
+    config: &Config,
+    asset: &Asset,
+    asset_name: &str,
+    videos: &[monitor::NewestVideo],
+    quiet: bool,
+    output: &mut Output,
+) -> Vec<String> {
+    let platform = monitor::get_platform(&asset.link).unwrap_or_default();
+    let cookies = config.cookies.as_ref().and_then(|c| c.get(platform));
+    let mut downloader = Downloader::new(config.download.as_ref());
+
+    let mut failed = Vec::new();
+    for video in videos.iter().filter(|v| v.is_new) {
+        let status = downloader.download(platform, asset, video, cookies);
+        if quiet {
+            output.out(format!("[{}] {} {}", asset_name, video.id, status));
+        } else {
+            output.out(format!("  {} {}", video.id, status));
+        }
+        if matches!(status, DownloadStatus::Failed(_)) {
+            failed.push(video.id.clone());
         }
+    }
+    failed
+}
+
+/// 添加资产
+fn add(config_path: Option<&str>, url: String, name: Option<String>) -> Result<(), String> {
+    // 配置文件不存在时，添加第一个资产会创建它
+    let config = match Path::new(config_helper::config_path(config_path)).exists() {
+        true => load_config(config_path)?,
+        false => Config::default(),
+    };
+    // 短链接需要请求平台
+    http::configure(config.http.as_ref()).map_err(|err| err.to_string())?;
+    // 保存规范的作者主页链接
+    let author = platform::resolve(&url).map_err(|err| err.to_string())?;
+    let url = author.url();
+    // 不同的链接可能指向同一作者
+    let key = Some(author.key());
+    if let Some(asset) = config
+        .assets
+        .iter()
+        .flatten()
+        .find(|asset| asset.author_key() == key)
+    {
+        return Err(format!("asset already exists: {}", asset.link));
+    }
+
+    config_helper::add_asset(config_path, name.as_deref(), &url)
+        .map_err(|err| format!("failed to save config: {}", err))?;
+    println!("Added {}", url);
+    Ok(())
+}
+
+/// 移除资产
+fn remove(config_path: Option<&str>, target: &str) -> Result<(), String> {
+    load_config(config_path)?;
+    let removed = config_helper::remove_assets(config_path, target)
+        .map_err(|err| format!("failed to save config: {}", err))?;
+    if removed == 0 {
+        return Err(format!("asset not found: {}", target));
+    }
 
-        // 换行
+    println!("Removed {} asset(s)", removed);
+    Ok(())
+}
+
+/// 列出所有资产
+fn list(config_path: Option<&str>) -> Result<(), String> {
+    let config = load_config(config_path)?;
+    for (index, asset) in config.assets.unwrap_or_default().iter().enumerate() {
+        println!(
+            "{:>3}. [{}] {}",
+            index + 1,
+            asset.name.as_deref().unwrap_or("NoN"),
+            asset.link
+        );
+    }
+    Ok(())
+}
+
+/// 查看偏移量历史
+fn history(
+    config_path: Option<&str>,
+    state_path: &Path,
+    target: Option<&str>,
+) -> Result<(), String> {
+    let config = load_config(config_path)?;
+    configure(&config)?;
+    let state = load_state(state_path, &config)?;
+    let assets = config.assets.unwrap_or_default();
+    let assets = assets
+        .iter()
+        .filter(|asset| target.is_none_or(|t| asset.matches(t)))
+        .collect::<Vec<_>>();
+    if let (Some(target), true) = (target, assets.is_empty()) {
+        return Err(format!("asset not found: {}", target));
+    }
+
+    for asset in assets {
+        println!(
+            "[{}]({})",
+            asset.name.as_deref().unwrap_or("NoN"),
+            asset.link
+        );
+        let mut offsets = state
+            .get(asset)
+            .map(|s| s.offsets.clone())
+            .unwrap_or_default();
+        if offsets.is_empty() {
+            println!("  No history.");
+        }
+        offsets.sort_by_key(|o| std::cmp::Reverse(o.date));
+        for offset in offsets {
+            let mark = match offset.mark.parse::<i64>() {
+                Ok(0) | Err(_) => offset.mark.clone(),
+                Ok(_) => format_date(&offset.mark).unwrap_or_else(|| offset.mark.clone()),
+            };
+            println!("  {} -> {}", offset.date, mark);
+        }
         println!();
     }
+    Ok(())
+}
 
-    // 更新设置
-    config_helper::save_config(&_config, config_path).unwrap();
+/// 扫码登录平台账号，并把 cookies 保存到状态文件
+fn login(
+    config_path: Option<&str>,
+    state_path: &Path,
+    platform: LoginPlatform,
+) -> Result<(), String> {
+    // 登录请求同样使用配置中的代理等设置
+    let config = load_config(config_path)?;
+    http::configure(config.http.as_ref()).map_err(|err| err.to_string())?;
 
-    // 等待结束
-    println!("Press Enter to exit...");
-    io::stdin().read_line(&mut String::new()).unwrap();
+    match platform {
+        LoginPlatform::Bilibili => {
+            let login = bilibili_login::login().map_err(|err| err.to_string())?;
+            state::update(state_path, |state| {
+                state.cookies.remove("bilibili");
+                state.bilibili_login = Some(login);
+            })?;
+        }
+    }
+    println!("Logged in, cookies saved to {}", state_path.display());
+    Ok(())
 }
 
-fn format_date(date: &str) -> String {
-    chrono::DateTime::from_timestamp(date.parse::<i64>().unwrap() / 1000, 0)
-        .unwrap()
-        .with_timezone(&chrono::Local)
-        .format("%m-%d %H:%M")
-        .to_string()
+/// 从浏览器导出的 cookies 中导入各平台的 cookies，并保存到状态文件
+fn import_cookies(state_path: &Path, path: &str) -> Result<(), String> {
+    let imported = cookie_import::import(Path::new(path)).map_err(|err| err.to_string())?;
+    if imported.is_empty() {
+        return Err(format!(
+            "no cookies of supported platforms found in {}",
+            path
+        ));
+    }
+
+    state::update(state_path, |state| {
+        for (platform, cookies) in &imported {
+            state
+                .cookies
+                .insert(platform.to_string(), http::merge_cookies("", cookies));
+            // 导入的 cookies 没有 refresh_token，替换扫码登录的结果
+            if *platform == "bilibili" {
+                state.bilibili_login = None;
+            }
+        }
+    })?;
+    for (platform, cookies) in &imported {
+        println!("Updated {} cookies: {} cookie(s)", platform, cookies.len());
+    }
+    Ok(())
+}
+
+/// 检查配置文件
+fn validate(config_path: Option<&str>) -> Result<(), String> {
+    let config = load_config(config_path)?;
+
+    let mut problems = Vec::new();
+    if let Err(err) = platform::configure(config.platforms.as_ref()) {
+        problems.push(err.to_string());
+    }
+    if let Err(err) = http::configure(config.http.as_ref()) {
+        problems.push(err.to_string());
+    }
+
+    let duplicates = config.duplicate_assets();
+    let duplicate_ids = config.duplicate_ids();
+    let assets = config.assets.unwrap_or_default();
+    for asset in &assets {
+        match platform::resolve(&asset.link) {
+            Ok(author) => {
+                let platform = author.platform;
+                // 资产启用了平台不支持的功能
+                let capabilities = platform.capabilities();
+                let live = asset.live == Some(true) || asset.record_live == Some(true);
+                if live && !capabilities.live {
+                    problems.push(format!("{}: {}", asset.link, platform.unsupported("live")));
+                }
+                if asset.download == Some(true) && !capabilities.download {
+                    let err = platform.unsupported("download");
+                    problems.push(format!("{}: {}", asset.link, err));
+                }
+            }
+            Err(err) => problems.push(format!("{}: {}", asset.link, err)),
+        }
+    }
+    for (index, first) in duplicates {
+        problems.push(format!(
+            "{}: same author as {}",
+            assets[index].link, assets[first].link
+        ));
+    }
+    for (index, first) in duplicate_ids {
+        problems.push(format!(
+            "{}: same id as {}",
+            assets[index].link, assets[first].link
+        ));
+    }
+
+    if !problems.is_empty() {
+        for problem in &problems {
+            eprintln!("{}", problem);
+        }
+        return Err(format!("{} problem(s) found", problems.len()));
+    }
+    println!("Config is valid, {} asset(s).", assets.len());
+    Ok(())
+}
+
+/// 把毫秒时间戳转换为本地时间，不是合法的时间戳时返回 None
+fn format_date(date: &str) -> Option<String> {
+    let date = chrono::DateTime::from_timestamp(date.parse::<i64>().ok()? / 1000, 0)?;
+    Some(
+        date.with_timezone(&chrono::Local)
+            .format("%m-%d %H:%M")
+            .to_string(),
+    )
 }
@@ -0,0 +1,704 @@
+use crate::config_helper::Asset;
+use crate::downloader::{MediaFile, MediaTask};
+use crate::error::VpmError;
+use crate::http;
+use crate::live::{LiveStatus, LiveStream};
+use crate::monitor::{
+    collect_video, field_str, field_u64, parse_offset, send_json, NewestVideo, VideoKind,
+};
+use crate::platform::{Capabilities, Platform};
+use crate::state::{self, WbiKeys};
+use crate::throttle;
+use rand::{thread_rng, Rng};
+use reqwest::blocking::RequestBuilder;
+use reqwest::header::{COOKIE, REFERER};
+use serde::Deserialize;
+use serde_json::Value;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use url::Url;
+
+const MONITOR_API: &str = "https://api.bilibili.com/x/space/wbi/arc/search";
+const SPACE_REFERER: &str = "https://space.bilibili.com/";
+const PAGE_SIZE: u32 = 10;
+const VIEW_API: &str = "https://api.bilibili.com/x/web-interface/view";
+const PLAYURL_API: &str = "https://api.bilibili.com/x/player/wbi/playurl";
+const VIDEO_REFERER: &str = "https://www.bilibili.com/";
+
+// 清晰度名称与 qn 的对应关系
+const QUALITIES: [(&str, u64); 11] = [
+    ("8K", 127),
+    ("DOLBY", 126),
+    ("HDR", 125),
+    ("4K", 120),
+    ("1080P60", 116),
+    ("1080P+", 112),
+    ("1080P", 80),
+    ("720P60", 74),
+    ("720P", 64),
+    ("480P", 32),
+    ("360P", 16),
+];
+
+const ROOM_INFO_API: &str = "https://api.live.bilibili.com/room/v1/Room/getRoomInfoOld";
+const LIVE_PLAY_URL_API: &str = "https://api.live.bilibili.com/room/v1/Room/playUrl";
+const LIVE_REFERER: &str = "https://live.bilibili.com/";
+
+// 并发检查时多个线程共享同一份 wbi keys，首次使用时从状态文件读取
+static WBI_KEYS: Mutex<Option<WbiKeys>> = Mutex::new(None);
+
+pub(super) struct Bilibili;
+impl Platform for Bilibili {
+    fn name(&self) -> &'static str {
+        "bilibili"
+    }
+
+    fn hosts(&self) -> &'static [&'static str] {
+        &["space.bilibili.com", "m.bilibili.com", "www.bilibili.com"]
+    }
+
+    fn short_hosts(&self) -> &'static [&'static str] {
+        &["b23.tv", "bili2233.cn"]
+    }
+
+    fn user_id(&self, url: &Url) -> Option<String> {
+        let mut segments = url.path_segments()?.filter(|s| !s.is_empty());
+        let mid = match url.host_str()? {
+            // e.g. https://space.bilibili.com/1344420936/video
+            "space.bilibili.com" => segments.next(),
+            // e.g. https://m.bilibili.com/space/1344420936
+            _ => segments.skip_while(|s| *s != "space").nth(1),
+        }?;
+        mid.bytes()
+            .all(|b| b.is_ascii_digit())
+            .then(|| mid.to_string())
+    }
+
+    fn profile_url(&self, id: &str) -> String {
+        format!("{}{}", SPACE_REFERER, id)
+    }
+
+    fn cookie_domain(&self) -> &'static str {
+        "bilibili.com"
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            videos: true,
+            live: true,
+            download: true,
+        }
+    }
+
+    fn newest_videos(
+        &self,
+        id: &str,
+        cookies: Option<&str>,
+        offset: Option<&str>,
+        max_pages: u32,
+    ) -> Result<(Vec<NewestVideo>, String), VpmError> {
+        let mid = id;
+
+        let offset = parse_offset(offset)?;
+        let mut videos = Vec::new();
+        let mut next_offset: u64 = 0;
+
+        let referer = format!("{}{}/video", SPACE_REFERER, mid);
+
+        for pn in 1..=max_pages {
+            let (dm_img_str, dm_cover_img_str) = self.gen_random_dm();
+
+            // url query parameters
+            let params = vec![
+                ("mid", mid.to_string()),
+                ("pn", pn.to_string()),
+                ("ps", PAGE_SIZE.to_string()),
+                ("index", "1".to_string()),
+                ("order", "pubdate".to_string()),
+                ("order_avoided", "true".to_string()),
+                ("platform", "web".to_string()),
+                ("web_location", "1550101".to_string()),
+                ("dm_img_list", "[]".to_string()),
+                ("dm_img_str", dm_img_str),
+                ("dm_cover_img_str", dm_cover_img_str),
+                (
+                    "dm_img_inter",
+                    r#"{"ds":[],"wh":[0,0,0],"of":[0,0,0]}"#.to_string(),
+                ),
+            ];
+
+            // get the newest video
+            let json = self.send_wbi(cookies.unwrap_or(""), &params, |query| {
+                let json = send_bilibili_json(
+                    http::client(self.name())?
+                        .get(format!("{}?{}", MONITOR_API, query))
+                        // referer
+                        .header("referer", &referer)
+                        // cookies
+                        .header(COOKIE, cookies.unwrap_or("")),
+                )?;
+                self.check_code(&json)?;
+                Ok(json)
+            })?;
+
+            // data -> list -> vlist
+            let vlist = json["data"]["list"]["vlist"]
+                .as_array()
+                .ok_or_else(|| VpmError::Parse("missing field `data.list.vlist`".to_string()))?;
+            let mut reached = false;
+            for video in vlist {
+                let id = field_str(video, "/bvid")?;
+                let title = field_str(video, "/title")?;
+                let url = format!("https://www.bilibili.com/video/{}", id);
+                let date = field_u64(video, "/created")? * 1000;
+
+                let video = NewestVideo {
+                    id: id.to_string(),
+                    title: title.to_string(),
+                    url,
+                    date: date.to_string(),
+                    is_new: true,
+                    kind: VideoKind::Video,
+                    raw: Value::Null,
+                };
+                reached |= collect_video(&mut videos, &mut next_offset, video, date, offset);
+            }
+
+            // data -> page -> count
+            let count = json["data"]["page"]["count"].as_u64().unwrap_or(0);
+            let has_more = u64::from(pn * PAGE_SIZE) < count;
+            if offset.is_none() || reached || !has_more {
+                break;
+            }
+        }
+
+        Ok((videos, next_offset.to_string()))
+    }
+
+    fn live_status(&self, mid: &str, cookies: Option<&str>) -> Result<LiveStatus, VpmError> {
+        let json = throttle::request("bilibili", || {
+            let json = send_bilibili_json(
+                http::client("bilibili")?
+                    .get(ROOM_INFO_API)
+                    .query(&[("mid", mid)])
+                    .header(REFERER, LIVE_REFERER)
+                    .header(COOKIE, cookies.unwrap_or("")),
+            )?;
+            check_live_code(&json)?;
+            Ok(json)
+        })?;
+
+        let data = &json["data"];
+        let room_id = field_u64(data, "/roomid")?;
+        Ok(LiveStatus {
+            living: data["liveStatus"].as_i64() == Some(1),
+            title: data["title"].as_str().unwrap_or_default().to_string(),
+            room_url: format!("{}{}", LIVE_REFERER, room_id),
+            room_id: room_id.to_string(),
+            raw: json,
+        })
+    }
+
+    fn live_stream(
+        &self,
+        status: &LiveStatus,
+        cookies: Option<&str>,
+    ) -> Result<LiveStream, VpmError> {
+        // qn=10000 原画
+        let json = throttle::request("bilibili", || {
+            let json = send_bilibili_json(
+                http::client("bilibili")?
+                    .get(LIVE_PLAY_URL_API)
+                    .query(&[
+                        ("cid", status.room_id.as_str()),
+                        ("qn", "10000"),
+                        ("platform", "web"),
+                    ])
+                    .header(REFERER, LIVE_REFERER)
+                    .header(COOKIE, cookies.unwrap_or("")),
+            )?;
+            check_live_code(&json)?;
+            Ok(json)
+        })?;
+
+        let url = json["data"]["durl"]
+            .as_array()
+            .and_then(|list| list.first())
+            .and_then(|d| d["url"].as_str())
+            .ok_or_else(|| VpmError::Parse("missing field `data.durl`".to_string()))?;
+        Ok(LiveStream {
+            url: url.to_string(),
+            referer: LIVE_REFERER.to_string(),
+        })
+    }
+
+    fn resolve_media(
+        &self,
+        asset: &Asset,
+        video: &NewestVideo,
+        cookies: Option<&str>,
+    ) -> Result<MediaTask, VpmError> {
+        let bvid = video.id.as_str();
+
+        // bvid -> cid
+        let view = throttle::request("bilibili", || {
+            let view = send_bilibili_json(
+                http::client("bilibili")?
+                    .get(VIEW_API)
+                    .query(&[("bvid", bvid)])
+                    .header(REFERER, VIDEO_REFERER)
+                    .header(COOKIE, cookies.unwrap_or("")),
+            )?;
+            self.check_code(&view)?;
+            Ok(view)
+        })?;
+        let cid = view["data"]["cid"]
+            .as_u64()
+            .ok_or_else(|| VpmError::Parse("missing field `data.cid`".to_string()))?;
+
+        // 获取 DASH 播放地址，fnval=4048 请求所有 DASH 格式
+        let params = vec![
+            ("bvid", bvid.to_string()),
+            ("cid", cid.to_string()),
+            ("qn", "127".to_string()),
+            ("fnval", "4048".to_string()),
+            ("fnver", "0".to_string()),
+            ("fourk", "1".to_string()),
+        ];
+        let play = self.send_wbi(cookies.unwrap_or(""), &params, |query| {
+            let play = send_bilibili_json(
+                http::client("bilibili")?
+                    .get(format!("{}?{}", PLAYURL_API, query))
+                    .header(REFERER, VIDEO_REFERER)
+                    .header(COOKIE, cookies.unwrap_or("")),
+            )?;
+            self.check_code(&play)?;
+            Ok(play)
+        })?;
+
+        let dash = &play["data"]["dash"];
+        let streams = dash["video"]
+            .as_array()
+            .ok_or_else(|| VpmError::Parse("missing field `data.dash.video`".to_string()))?;
+
+        // 返回的视频流只包含当前登录状态可用的清晰度，选择不高于设置的最高清晰度
+        let max_qn = asset
+            .quality
+            .as_deref()
+            .and_then(quality_qn)
+            .unwrap_or(u64::MAX);
+        let qn = streams
+            .iter()
+            .filter_map(|s| s["id"].as_u64())
+            .filter(|qn| *qn <= max_qn)
+            .max()
+            .or_else(|| streams.iter().filter_map(|s| s["id"].as_u64()).min())
+            .ok_or_else(|| VpmError::Parse("no video stream available".to_string()))?;
+
+        // 同一清晰度下优先选择设置的编码，否则选择码率最高的
+        let codec_id = asset.codec.as_deref().and_then(codec_id);
+        let video_stream = streams
+            .iter()
+            .filter(|s| s["id"].as_u64() == Some(qn))
+            .max_by_key(|s| {
+                (
+                    codec_id.is_some() && s["codecid"].as_u64() == codec_id,
+                    s["bandwidth"].as_u64().unwrap_or(0),
+                )
+            })
+            .and_then(stream_url)
+            .ok_or_else(|| VpmError::Parse("missing video stream url".to_string()))?;
+
+        // 音频流：Hi-Res 与杜比全景声优先，否则选择码率最高的
+        let audio_stream = dash["flac"]["audio"]
+            .as_object()
+            .map(|_| &dash["flac"]["audio"])
+            .or_else(|| dash["dolby"]["audio"].as_array().and_then(|a| a.first()))
+            .or_else(|| {
+                dash["audio"].as_array().and_then(|a| {
+                    a.iter()
+                        .max_by_key(|s| s["bandwidth"].as_u64().unwrap_or(0))
+                })
+            })
+            .and_then(stream_url);
+
+        let mut files = vec![MediaFile {
+            url: video_stream,
+            suffix: "video.m4s".to_string(),
+            referer: Some(VIDEO_REFERER.to_string()),
+        }];
+        if let Some(audio_stream) = audio_stream {
+            files.push(MediaFile {
+                url: audio_stream,
+                suffix: "audio.m4s".to_string(),
+                referer: Some(VIDEO_REFERER.to_string()),
+            });
+        }
+
+        Ok(MediaTask {
+            files,
+            merge: Some(asset.container.clone().unwrap_or("mp4".to_string())),
+        })
+    }
+}
+
+// ================================================================================================
+// Wbi
+// ================================================================================================
+
+#[derive(Deserialize)]
+struct WbiImg {
+    img_url: String,
+    sub_url: String,
+}
+
+#[derive(Deserialize)]
+struct Data {
+    wbi_img: WbiImg,
+}
+
+#[derive(Deserialize)]
+struct ResWbi {
+    data: Data,
+}
+
+/// wbi key 为 32 位 ASCII 字符
+fn is_wbi_key(key: &str) -> bool {
+    key.len() == 32 && key.is_ascii()
+}
+
+/// 发送请求并解析响应 JSON
+///
+/// 请求被拦截时 HTTP 状态码为 412，响应体不一定是 JSON，与接口返回的 -412 同样按风控处理
+fn send_bilibili_json(request: RequestBuilder) -> Result<Value, VpmError> {
+    send_json(request).map_err(blocked)
+}
+
+/// 将 HTTP 412 转换为风控错误
+fn blocked(err: VpmError) -> VpmError {
+    match err {
+        VpmError::HttpStatus { status: 412, url } => VpmError::RiskControl {
+            platform: "bilibili",
+            code: -412,
+            message: format!("request blocked: {}", url),
+        },
+        err => err,
+    }
+}
+
+impl Bilibili {
+    /// 检查接口返回的 code
+    fn check_code(&self, json: &Value) -> Result<(), VpmError> {
+        let code = json["code"]
+            .as_i64()
+            .ok_or_else(|| VpmError::Parse("missing field `code`".to_string()))?;
+        let message = json["message"].as_str().unwrap_or_default().to_string();
+        match code {
+            0 => Ok(()),
+            // -352 风控校验失败，-412 请求被拦截
+            -352 | -412 => Err(VpmError::RiskControl {
+                platform: "bilibili",
+                code,
+                message,
+            }),
+            // -101 账号未登录
+            -101 => Err(VpmError::AuthExpired {
+                platform: "bilibili",
+                message,
+            }),
+            _ => Err(VpmError::Api {
+                platform: "bilibili",
+                code,
+                message,
+            }),
+        }
+    }
+
+    fn gen_mixin_key(&self, raw_wbi_key: impl AsRef<[u8]>) -> String {
+        const MIXIN_KEY_ENC_TAB: [u8; 64] = [
+            46, 47, 18, 2, 53, 8, 23, 32, 15, 50, 10, 31, 58, 3, 45, 35, 27, 43, 5, 49, 33, 9, 42,
+            19, 29, 28, 14, 39, 12, 38, 41, 13, 37, 48, 7, 16, 24, 55, 40, 61, 26, 17, 0, 1, 60,
+            51, 30, 4, 22, 25, 54, 21, 56, 59, 6, 63, 57, 62, 11, 36, 20, 34, 44, 52,
+        ];
+        let raw_wbi_key = raw_wbi_key.as_ref();
+        let mut mixin_key = {
+            let binding = MIXIN_KEY_ENC_TAB
+                .iter()
+                // 此步操作即遍历 MIXIN_KEY_ENC_TAB，取出 raw_wbi_key 中对应位置的字符
+                .map(|n| raw_wbi_key[*n as usize])
+                // 并收集进数组内
+                .collect::<Vec<u8>>();
+            unsafe { String::from_utf8_unchecked(binding) }
+        };
+        let _ = mixin_key.split_off(32); // 截取前 32 位字符
+        mixin_key
+    }
+
+    fn get_url_encoded(&self, s: &str) -> String {
+        s.chars()
+            .filter_map(|c| match c.is_ascii_alphanumeric() || "-_.~".contains(c) {
+                true => Some(c.to_string()),
+                false => {
+                    // 过滤 value 中的 "!'()*" 字符
+                    if "!'()*".contains(c) {
+                        return None;
+                    }
+                    let encoded = c
+                        .encode_utf8(&mut [0; 4])
+                        .bytes()
+                        .fold("".to_string(), |acc, b| acc + &format!("%{:02X}", b));
+                    Some(encoded)
+                }
+            })
+            .collect::<String>()
+    }
+
+    // 为请求参数进行 wbi 签名
+    fn encode_wbi(&self, params: &mut Vec<(&str, String)>, keys: &WbiKeys) -> String {
+        let mixin_key = self.gen_mixin_key(format!("{}{}", keys.img_key, keys.sub_key).as_bytes());
+        let cur_time = match SystemTime::now().duration_since(UNIX_EPOCH) {
+            Ok(t) => t.as_secs(),
+            Err(_) => panic!("SystemTime before UNIX EPOCH!"),
+        };
+        // 添加当前时间戳
+        params.push(("wts", cur_time.to_string()));
+        // 重新排序
+        params.sort_by(|a, b| a.0.cmp(b.0));
+        let query = params.iter().fold(String::from(""), |acc, (k, v)| {
+            acc + format!("{}={}&", self.get_url_encoded(k), self.get_url_encoded(v)).as_str()
+        });
+
+        // query截取最后一个字符
+        let query = query.trim_end_matches('&').to_string();
+
+        let web_sign = format!("{:?}", md5::compute(query.clone() + &mixin_key));
+
+        query + &format!("&w_rid={}", web_sign)
+    }
+
+    /// 对请求参数进行 wbi 签名后发送请求，签名校验失败时刷新 wbi keys 并重试一次
+    ///
+    /// `send` 的参数为签名后的 query。获取 keys 和发送请求分别经过限流，调用方不能再套一层
+    /// [`throttle::request`]，否则重试次数会叠加，一次风控也会触发两次冷却
+    fn send_wbi(
+        &self,
+        cookies: &str,
+        params: &[(&str, String)],
+        send: impl Fn(&str) -> Result<Value, VpmError>,
+    ) -> Result<Value, VpmError> {
+        let signed = |keys: &WbiKeys| {
+            throttle::request(self.name(), || {
+                send(&self.encode_wbi(&mut params.to_vec(), keys))
+            })
+        };
+        let keys = self.get_wbi_keys(cookies, None)?;
+        match signed(&keys) {
+            // -403 访问权限不足，wbi keys 过期时返回
+            Err(VpmError::Api { code: -403, .. }) => {
+                let keys = self.get_wbi_keys(cookies, Some(&keys))?;
+                signed(&keys)
+            }
+            result => result,
+        }
+    }
+
+    /// 获取 wbi keys
+    ///
+    /// 缓存的 keys 不是北京时间今天获取的，或与被拒绝的 `rejected` 相同时重新获取
+    fn get_wbi_keys(&self, cookies: &str, rejected: Option<&WbiKeys>) -> Result<WbiKeys, VpmError> {
+        let mut cached = WBI_KEYS.lock().unwrap();
+        if cached.is_none() {
+            *cached = state::read_current()
+                .and_then(|state| state.wbi_keys)
+                .filter(|keys| is_wbi_key(&keys.img_key) && is_wbi_key(&keys.sub_key));
+        }
+        if let Some(keys) = cached
+            .as_ref()
+            .filter(|keys| keys.is_fresh() && Some(*keys) != rejected)
+        {
+            return Ok(keys.clone());
+        }
+
+        // get wbi keys
+        let ResWbi {
+            data: Data { wbi_img },
+        } = throttle::request(self.name(), || {
+            Ok(http::client(self.name())?
+                .get("https://api.bilibili.com/x/web-interface/nav")
+                // SESSDATA=xxxxx
+                .header("Cookie", cookies)
+                .send()?
+                .error_for_status()
+                .map_err(|err| blocked(err.into()))?
+                .json::<ResWbi>()?)
+        })?;
+
+        // 生成 mixin key 时按下标取字符，长度不对会越界
+        let key_of = |url: &str| -> Result<String, VpmError> {
+            url.rsplit('/')
+                .next()
+                .and_then(|name| name.split('.').next())
+                .filter(|key| is_wbi_key(key))
+                .map(|key| key.to_string())
+                .ok_or_else(|| VpmError::Parse(format!("invalid wbi key url: {}", url)))
+        };
+        let keys = WbiKeys::new(key_of(&wbi_img.img_url)?, key_of(&wbi_img.sub_url)?);
+
+        // save to cache and state file
+        *cached = Some(keys.clone());
+        if let Err(err) = state::update_current(|state| state.wbi_keys = Some(keys.clone())) {
+            eprintln!("Failed to save wbi keys: {}", err);
+        }
+
+        Ok(keys)
+    }
+
+    fn gen_random_dm(&self) -> (String, String) {
+        fn gen_random_str() -> String {
+            let source_string = "ABCDEFGHIJK";
+            let mut rng = thread_rng();
+            let random_index_1 = rng.gen_range(0..source_string.len());
+            let random_index_2 = rng.gen_range(0..source_string.len());
+            let random_char_1 = source_string.chars().nth(random_index_1).unwrap();
+            let random_char_2 = source_string.chars().nth(random_index_2).unwrap();
+            let random_string = format!("{}{}", random_char_1, random_char_2);
+            random_string
+        }
+
+        (gen_random_str(), gen_random_str())
+    }
+}
+
+// ================================================================================================
+// Live
+// ================================================================================================
+
+/// 检查直播接口返回的 code，风控错误码与视频接口相同
+fn check_live_code(json: &Value) -> Result<(), VpmError> {
+    match json["code"].as_i64() {
+        Some(0) => Ok(()),
+        Some(code @ (-352 | -412)) => Err(VpmError::RiskControl {
+            platform: "bilibili",
+            code,
+            message: json["message"].as_str().unwrap_or_default().to_string(),
+        }),
+        Some(code) => Err(VpmError::Api {
+            platform: "bilibili",
+            code,
+            message: json["message"].as_str().unwrap_or_default().to_string(),
+        }),
+        None => Err(VpmError::Parse("missing field `code`".to_string())),
+    }
+}
+
+// ================================================================================================
+// Download
+// ================================================================================================
+
+/// 将清晰度设置转换为 qn，支持名称（如 `1080P+`）或数字
+fn quality_qn(quality: &str) -> Option<u64> {
+    let quality = quality.trim().to_uppercase();
+    QUALITIES
+        .iter()
+        .find(|(name, _)| *name == quality)
+        .map(|(_, qn)| *qn)
+        .or_else(|| quality.parse().ok())
+}
+
+/// 将编码设置转换为 codecid
+fn codec_id(codec: &str) -> Option<u64> {
+    match codec.trim().to_lowercase().as_str() {
+        "avc" | "h264" => Some(7),
+        "hevc" | "h265" => Some(12),
+        "av1" => Some(13),
+        _ => None,
+    }
+}
+
+/// 获取 DASH 流地址，优先使用 baseUrl
+fn stream_url(stream: &Value) -> Option<String> {
+    stream["baseUrl"]
+        .as_str()
+        .or_else(|| stream["base_url"].as_str())
+        .map(|url| url.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{blocked, check_live_code, is_wbi_key, Bilibili};
+    use crate::error::VpmError;
+    use crate::platform::Platform;
+    use serde_json::json;
+    use url::Url;
+
+    #[test]
+    fn http_412_is_risk_control() {
+        let err = blocked(VpmError::HttpStatus {
+            status: 412,
+            url: "https://api.bilibili.com/x/space/wbi/arc/search".to_string(),
+        });
+        assert!(matches!(
+            err,
+            VpmError::RiskControl {
+                platform: "bilibili",
+                code: -412,
+                ..
+            }
+        ));
+
+        // 其他状态码保持不变，由限流模块决定是否重试
+        let err = blocked(VpmError::HttpStatus {
+            status: 503,
+            url: String::new(),
+        });
+        assert!(matches!(err, VpmError::HttpStatus { status: 503, .. }));
+    }
+
+    #[test]
+    fn live_risk_control() {
+        for code in [-352, -412] {
+            let err = check_live_code(&json!({ "code": code, "message": "blocked" }));
+            assert!(matches!(err, Err(VpmError::RiskControl { code: c, .. }) if c == code));
+        }
+        let err = check_live_code(&json!({ "code": -400, "message": "bad request" }));
+        assert!(matches!(err, Err(VpmError::Api { code: -400, .. })));
+        assert!(check_live_code(&json!({ "code": 0 })).is_ok());
+    }
+
+    #[test]
+    fn wbi_key_length() {
+        assert!(is_wbi_key("7cd084941338484aae1ad9425b84077c"));
+        assert!(!is_wbi_key("7cd084941338484aae1ad9425b84077"));
+        assert!(!is_wbi_key(""));
+        assert!(!is_wbi_key("7cd084941338484aae1ad9425b84077中"));
+    }
+
+    // (链接, 作者 id)
+    const LINKS: [(&str, Option<&str>); 8] = [
+        ("https://space.bilibili.com/1344420936", Some("1344420936")),
+        ("https://space.bilibili.com/1344420936/", Some("1344420936")),
+        (
+            "https://space.bilibili.com/1344420936/video?tid=0",
+            Some("1344420936"),
+        ),
+        (
+            "https://m.bilibili.com/space/1344420936",
+            Some("1344420936"),
+        ),
+        (
+            "https://www.bilibili.com/space/1344420936/",
+            Some("1344420936"),
+        ),
+        ("https://space.bilibili.com/", None),
+        ("https://space.bilibili.com/abc", None),
+        ("https://www.bilibili.com/video/BV1xx411c7mD", None),
+    ];
+
+    #[test]
+    fn user_id() {
+        for (link, id) in LINKS {
+            let url = Url::parse(link).unwrap();
+            assert_eq!(Bilibili.user_id(&url).as_deref(), id, "{}", link);
+        }
+    }
+}
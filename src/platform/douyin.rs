@@ -0,0 +1,439 @@
+use crate::config_helper::Asset;
+use crate::douyin_guest;
+use crate::downloader::{prefer_h265, MediaFile, MediaTask};
+use crate::error::VpmError;
+use crate::http;
+use crate::live::{LiveStatus, LiveStream};
+use crate::monitor::{
+    collect_video, field_str, field_u64, parse_offset, send_json, NewestVideo, VideoKind,
+};
+use crate::platform::{Capabilities, Defaults, Platform};
+use crate::throttle;
+use crate::{a_bogus, x_bogus};
+use reqwest::blocking::RequestBuilder;
+use reqwest::header::{COOKIE, REFERER};
+use serde::Deserialize;
+use serde_json::Value;
+use std::sync::RwLock;
+use toml::Table;
+use url::Url;
+
+const MONITOR_API: &str = "https://www.douyin.com/aweme/v1/web/aweme/post/";
+const VIDEO_REFERER: &str = "https://www.douyin.com/";
+const ROOM_API: &str = "https://webcast.amemv.com/webcast/room/reflow/info/";
+const LIVE_REFERER: &str = "https://live.douyin.com/";
+// default requests per minute, douyin returns empty responses when requested too often
+const DEFAULT_RATE: f64 = 10.0;
+
+// 配置文件中强制使用的签名方式
+static SIGN: RwLock<DouyinSign> = RwLock::new(DouyinSign::Auto);
+
+/// 抖音设置，位于配置文件的 `[platforms.douyin]`
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+struct DouyinConfig {
+    // 请求签名方式，默认按接口选择
+    sign: Option<DouyinSign>,
+}
+
+/// 抖音请求的签名方式
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum DouyinSign {
+    // 按接口选择
+    #[default]
+    Auto,
+    XBogus,
+    ABogus,
+}
+
+/// 为抖音请求参数追加签名
+///
+/// `default` 为接口默认使用的签名方式，配置中指定了签名方式时以配置为准
+fn sign(query: &str, default: DouyinSign) -> String {
+    let user_agent = http::user_agent("douyin");
+    let sign = match *SIGN.read().unwrap() {
+        DouyinSign::Auto => default,
+        sign => sign,
+    };
+    match sign {
+        DouyinSign::XBogus | DouyinSign::Auto => {
+            format!("{}&X-Bogus={}", query, x_bogus::sign(query, &user_agent))
+        }
+        DouyinSign::ABogus => {
+            let a_bogus = a_bogus::sign(query, &user_agent);
+            let a_bogus =
+                url::form_urlencoded::byte_serialize(a_bogus.as_bytes()).collect::<String>();
+            format!("{}&a_bogus={}", query, a_bogus)
+        }
+    }
+}
+
+pub(super) struct Douyin;
+impl Platform for Douyin {
+    fn name(&self) -> &'static str {
+        "douyin"
+    }
+
+    fn configure(&self, settings: Option<&Table>) -> Result<(), VpmError> {
+        let config = match settings {
+            Some(settings) => settings
+                .clone()
+                .try_into::<DouyinConfig>()
+                .map_err(|err| VpmError::Config(format!("platforms.douyin: {}", err)))?,
+            None => DouyinConfig::default(),
+        };
+        *SIGN.write().unwrap() = config.sign.unwrap_or_default();
+        Ok(())
+    }
+
+    fn hosts(&self) -> &'static [&'static str] {
+        &[
+            "www.douyin.com",
+            "douyin.com",
+            "m.douyin.com",
+            "www.iesdouyin.com",
+            "iesdouyin.com",
+        ]
+    }
+
+    fn short_hosts(&self) -> &'static [&'static str] {
+        &["v.douyin.com"]
+    }
+
+    fn user_id(&self, url: &Url) -> Option<String> {
+        // 分享页的 sec_uid 可能在参数中
+        if let Some((_, id)) = url.query_pairs().find(|(key, _)| key == "sec_uid") {
+            return Some(id.to_string());
+        }
+        // e.g. https://www.douyin.com/user/MS4wLjABAAAA
+        // e.g. https://www.iesdouyin.com/share/user/MS4wLjABAAAA
+        url.path_segments()?
+            .skip_while(|s| *s != "user")
+            .nth(1)
+            .filter(|id| !id.is_empty())
+            .map(|id| id.to_string())
+    }
+
+    fn profile_url(&self, id: &str) -> String {
+        format!("{}user/{}", VIDEO_REFERER, id)
+    }
+
+    fn cookie_domain(&self) -> &'static str {
+        "douyin.com"
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            videos: true,
+            live: true,
+            download: true,
+        }
+    }
+
+    fn defaults(&self) -> Defaults {
+        Defaults {
+            rate: Some(DEFAULT_RATE),
+            ..Defaults::default()
+        }
+    }
+
+    fn newest_videos(
+        &self,
+        id: &str,
+        cookies: Option<&str>,
+        offset: Option<&str>,
+        max_pages: u32,
+    ) -> Result<(Vec<NewestVideo>, String), VpmError> {
+        let offset = parse_offset(offset)?;
+        let mut videos = Vec::new();
+        let mut next_offset: u64 = 0;
+        let mut max_cursor = 0;
+
+        for _ in 0..max_pages {
+            let query = format!("aid=6383&sec_user_id={}&count=10&max_cursor={}&cookie_enabled=true&platform=PC&downlink=10", id, max_cursor);
+
+            let json = throttle::request(self.name(), || {
+                let json = douyin_guest::send(cookies, |session| {
+                    // 游客身份的 msToken、verifyFp 需要参与签名
+                    let query = url::form_urlencoded::Serializer::new(query.clone())
+                        .extend_pairs(&session.params)
+                        .finish();
+                    let query = sign(&query, DouyinSign::ABogus);
+                    let api = format!("{}?{}", MONITOR_API, query);
+                    send_douyin_json(
+                        http::client(self.name())?
+                            .get(&api)
+                            .header(COOKIE, &session.cookie),
+                    )
+                })?;
+                if let Some(code) = json["status_code"].as_i64().filter(|code| *code != 0) {
+                    return Err(VpmError::Api {
+                        platform: "douyin",
+                        code,
+                        message: json["status_msg"].as_str().unwrap_or_default().to_string(),
+                    });
+                }
+                Ok(json)
+            })?;
+
+            let vlist = json["aweme_list"]
+                .as_array()
+                .ok_or_else(|| VpmError::Parse("missing field `aweme_list`".to_string()))?;
+            let mut reached = false;
+            for video in vlist {
+                let id = field_str(video, "/aweme_id")?;
+                let title = field_str(video, "/desc")?;
+                let url = format!("https://www.douyin.com/video/{}", id);
+                let date = field_u64(video, "/create_time")? * 1000;
+                // 置顶视频不按发布时间排列
+                let pinned = video["is_top"].as_i64().unwrap_or(0) == 1;
+
+                let video = NewestVideo {
+                    id: id.to_string(),
+                    title: title.to_string(),
+                    url,
+                    date: date.to_string(),
+                    is_new: true,
+                    kind: VideoKind::Video,
+                    raw: video.clone(),
+                };
+                reached |=
+                    collect_video(&mut videos, &mut next_offset, video, date, offset) && !pinned;
+            }
+
+            // has_more 为 1 时以 max_cursor 获取下一页
+            max_cursor = json["max_cursor"].as_u64().unwrap_or(0);
+            let has_more = json["has_more"].as_i64().unwrap_or(0) == 1 && max_cursor != 0;
+            if offset.is_none() || reached || !has_more {
+                break;
+            }
+        }
+
+        Ok((videos, next_offset.to_string()))
+    }
+
+    fn live_status(
+        &self,
+        sec_user_id: &str,
+        cookies: Option<&str>,
+    ) -> Result<LiveStatus, VpmError> {
+        let json = throttle::request("douyin", || {
+            let json = douyin_guest::send(cookies, |session| {
+                send_douyin_json(
+                    http::client("douyin")?
+                        .get(ROOM_API)
+                        .query(&[
+                            ("type_id", "0"),
+                            ("live_id", "1"),
+                            ("room_id", "2"),
+                            ("version_code", "99.99.99"),
+                            ("app_id", "1128"),
+                            ("sec_user_id", sec_user_id),
+                        ])
+                        .query(&session.params)
+                        .header(REFERER, LIVE_REFERER)
+                        .header(COOKIE, &session.cookie),
+                )
+            })?;
+            if let Some(code) = json["status_code"].as_i64().filter(|code| *code != 0) {
+                return Err(VpmError::Api {
+                    platform: "douyin",
+                    code,
+                    message: json["data"]["message"]
+                        .as_str()
+                        .unwrap_or_default()
+                        .to_string(),
+                });
+            }
+            Ok(json)
+        })?;
+
+        let room = &json["data"]["room"];
+        let web_rid = room["owner"]["web_rid"].as_str().unwrap_or_default();
+        Ok(LiveStatus {
+            // status: 2 直播中，4 已结束
+            living: room["status"].as_i64() == Some(2),
+            title: room["title"].as_str().unwrap_or_default().to_string(),
+            room_url: format!("{}{}", LIVE_REFERER, web_rid),
+            room_id: room["id_str"].as_str().unwrap_or_default().to_string(),
+            raw: room.clone(),
+        })
+    }
+
+    fn live_stream(
+        &self,
+        status: &LiveStatus,
+        _cookies: Option<&str>,
+    ) -> Result<LiveStream, VpmError> {
+        let stream_url = &status.raw["stream_url"];
+        // 按清晰度从高到低选择
+        let pick = |urls: &Value| {
+            ["FULL_HD1", "HD1", "SD1", "SD2"]
+                .iter()
+                .find_map(|quality| urls[quality].as_str())
+                .or_else(|| urls.as_object()?.values().find_map(|url| url.as_str()))
+                .map(|url| url.to_string())
+        };
+        let url = pick(&stream_url["flv_pull_url"])
+            .or_else(|| pick(&stream_url["hls_pull_url_map"]))
+            .ok_or_else(|| VpmError::Parse("missing field `stream_url`".to_string()))?;
+        Ok(LiveStream {
+            url,
+            referer: LIVE_REFERER.to_string(),
+        })
+    }
+
+    fn resolve_media(
+        &self,
+        asset: &Asset,
+        video: &NewestVideo,
+        _cookies: Option<&str>,
+    ) -> Result<MediaTask, VpmError> {
+        let aweme = &video.raw;
+        if aweme.is_null() {
+            return Err(VpmError::Parse("missing aweme detail".to_string()));
+        }
+        let referer = Some(VIDEO_REFERER.to_string());
+
+        // 图文作品：保存所有图片和背景音乐
+        if let Some(images) = aweme["images"].as_array().filter(|i| !i.is_empty()) {
+            let mut files = Vec::new();
+            for (index, image) in images.iter().enumerate() {
+                let url = first_url(image)
+                    .ok_or_else(|| VpmError::Parse(format!("missing url of image {}", index)))?;
+                let ext = extension(&url, "jpeg");
+                files.push(MediaFile {
+                    url,
+                    suffix: format!("{:02}.{}", index + 1, ext),
+                    referer: referer.clone(),
+                });
+            }
+            if let Some(url) = first_url(&aweme["music"]["play_url"]) {
+                let ext = extension(&url, "mp3");
+                files.push(MediaFile {
+                    url,
+                    suffix: format!("music.{}", ext),
+                    referer: referer.clone(),
+                });
+            }
+            return Ok(MediaTask { files, merge: None });
+        }
+
+        // 视频作品：在 bit_rate 中选择码率最高的无水印地址
+        let prefer_h265 = prefer_h265(asset);
+        let url = aweme["video"]["bit_rate"]
+            .as_array()
+            .and_then(|rates| {
+                rates
+                    .iter()
+                    .filter(|rate| first_url(&rate["play_addr"]).is_some())
+                    .max_by_key(|rate| {
+                        let is_h265 = rate["is_h265"].as_u64() == Some(1);
+                        (
+                            prefer_h265.is_some_and(|prefer| prefer == is_h265),
+                            rate["bit_rate"].as_u64().unwrap_or(0),
+                        )
+                    })
+            })
+            .and_then(|rate| first_url(&rate["play_addr"]))
+            .or_else(|| first_url(&aweme["video"]["play_addr"]))
+            .ok_or_else(|| VpmError::Parse("missing field `video.play_addr`".to_string()))?;
+
+        Ok(MediaTask {
+            files: vec![MediaFile {
+                url,
+                suffix: "mp4".to_string(),
+                referer,
+            }],
+            merge: None,
+        })
+    }
+}
+
+/// 发送抖音请求，请求过于频繁时抖音会返回空响应，视为触发风控
+fn send_douyin_json(request: RequestBuilder) -> Result<Value, VpmError> {
+    send_json(request).map_err(|err| match err {
+        VpmError::EmptyResponse => VpmError::RiskControl {
+            platform: "douyin",
+            code: 0,
+            message: "empty response".to_string(),
+        },
+        err => err,
+    })
+}
+
+// ================================================================================================
+// Download
+// ================================================================================================
+
+/// 取 url_list 中的第一个地址
+fn first_url(addr: &Value) -> Option<String> {
+    addr["url_list"]
+        .as_array()
+        .and_then(|list| list.first())
+        .and_then(|url| url.as_str())
+        // playwm 为带水印的地址
+        .map(|url| url.replace("/playwm/", "/play/"))
+}
+
+/// 根据地址推断文件后缀
+fn extension(url: &str, default: &str) -> String {
+    let path = url.split('?').next().unwrap_or(url);
+    for ext in ["jpeg", "jpg", "webp", "png", "heic", "mp3", "m4a"] {
+        if path.ends_with(&format!(".{}", ext)) || path.contains(&format!(".{}~", ext)) {
+            return ext.to_string();
+        }
+    }
+    default.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Douyin, DouyinSign, SIGN};
+    use crate::platform::Platform;
+    use url::Url;
+
+    #[test]
+    fn configure() {
+        let settings = |settings: &str| settings.parse::<toml::Table>().unwrap();
+        Douyin
+            .configure(Some(&settings("sign = 'a_bogus'")))
+            .unwrap();
+        assert_eq!(*SIGN.read().unwrap(), DouyinSign::ABogus);
+        assert!(Douyin.configure(Some(&settings("sign = 'md5'"))).is_err());
+        assert!(Douyin
+            .configure(Some(&settings("sigm = 'a_bogus'")))
+            .is_err());
+        // 没有设置时恢复为按接口选择
+        Douyin.configure(None).unwrap();
+        assert_eq!(*SIGN.read().unwrap(), DouyinSign::Auto);
+    }
+
+    const SEC_UID: &str = "MS4wLjABAAAAv7iSuuXDJGDvJkmH_vz1qkDZYo1apxgzaxdBSeIuPiM";
+
+    // (链接, 是否能解析出 SEC_UID)
+    const LINKS: [(&str, bool); 8] = [
+        ("https://www.douyin.com/user/{}", true),
+        ("https://www.douyin.com/user/{}/", true),
+        (
+            "https://www.douyin.com/user/{}?vid=7300000000000000000",
+            true,
+        ),
+        ("https://m.douyin.com/share/user/{}", true),
+        ("https://www.iesdouyin.com/share/user/{}?sec_uid={}", true),
+        ("https://www.iesdouyin.com/share/user/123?sec_uid={}", true),
+        ("https://www.douyin.com/user/", false),
+        ("https://www.douyin.com/video/7300000000000000000", false),
+    ];
+
+    #[test]
+    fn user_id() {
+        for (link, matched) in LINKS {
+            let link = link.replace("{}", SEC_UID);
+            let url = Url::parse(&link).unwrap();
+            let expected = matched.then_some(SEC_UID);
+            assert_eq!(Douyin.user_id(&url).as_deref(), expected, "{}", link);
+        }
+    }
+}
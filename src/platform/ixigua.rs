@@ -0,0 +1,267 @@
+use crate::config_helper::Asset;
+use crate::downloader::{prefer_h265, MediaFile, MediaTask};
+use crate::error::VpmError;
+use crate::http;
+use crate::monitor::{
+    collect_video, field_str, field_u64, parse_offset, send_json, NewestVideo, VideoKind,
+};
+use crate::platform::{Capabilities, Platform};
+use crate::throttle;
+use base64::Engine;
+use reqwest::header::{COOKIE, REFERER};
+use serde_json::Value;
+use url::Url;
+
+const MONITOR_API: &str = "https://www.ixigua.com/api/videov2/author/new_video_list";
+const PAGE_SIZE: u32 = 10;
+const VIDEO_PAGE: &str = "https://www.ixigua.com/";
+const SSR_DATA_PREFIX: &str = "window._SSR_HYDRATED_DATA=";
+
+pub(super) struct IXigua;
+impl Platform for IXigua {
+    fn name(&self) -> &'static str {
+        "ixigua"
+    }
+
+    fn hosts(&self) -> &'static [&'static str] {
+        &["www.ixigua.com", "ixigua.com", "m.ixigua.com"]
+    }
+
+    fn short_hosts(&self) -> &'static [&'static str] {
+        &["v.ixigua.com"]
+    }
+
+    fn user_id(&self, url: &Url) -> Option<String> {
+        // e.g. https://www.ixigua.com/home/2497727299858013/
+        url.path_segments()?
+            .skip_while(|s| *s != "home")
+            .nth(1)
+            .filter(|id| !id.is_empty() && id.bytes().all(|b| b.is_ascii_digit()))
+            .map(|id| id.to_string())
+    }
+
+    fn profile_url(&self, id: &str) -> String {
+        format!("{}home/{}/", VIDEO_PAGE, id)
+    }
+
+    fn cookie_domain(&self) -> &'static str {
+        "ixigua.com"
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            videos: true,
+            live: false,
+            download: true,
+        }
+    }
+
+    fn newest_videos(
+        &self,
+        id: &str,
+        cookies: Option<&str>,
+        offset: Option<&str>,
+        max_pages: u32,
+    ) -> Result<(Vec<NewestVideo>, String), VpmError> {
+        let referer = self.profile_url(id);
+
+        let offset = parse_offset(offset)?;
+        let mut videos = Vec::new();
+        let mut next_offset: u64 = 0;
+
+        for page in 0..max_pages {
+            let json = throttle::request(self.name(), || {
+                let json = send_json(
+                    http::client(self.name())?
+                        .get(MONITOR_API)
+                        .header("referer", &referer)
+                        .header(COOKIE, cookies.unwrap_or(""))
+                        .query(&[
+                            ("to_user_id", id),
+                            ("offset", &(page * PAGE_SIZE).to_string()),
+                            ("limit", &PAGE_SIZE.to_string()),
+                            ("order", "new"),
+                        ]),
+                )?;
+                if let Some(code) = json["code"].as_i64().filter(|code| *code != 0) {
+                    return Err(VpmError::Api {
+                        platform: "ixigua",
+                        code,
+                        message: json["message"].as_str().unwrap_or_default().to_string(),
+                    });
+                }
+                Ok(json)
+            })?;
+
+            let vlist = json["data"]["videoList"]
+                .as_array()
+                .ok_or_else(|| VpmError::Parse("missing field `data.videoList`".to_string()))?;
+            let mut reached = false;
+            for video in vlist {
+                let id = field_str(video, "/item_id")?;
+                let title = field_str(video, "/title")?;
+                let url = format!("https://www.ixigua.com/{}", id);
+                let date = field_u64(video, "/publish_time")? * 1000;
+
+                let video = NewestVideo {
+                    id: id.to_string(),
+                    title: title.to_string(),
+                    url,
+                    date: date.to_string(),
+                    is_new: true,
+                    kind: VideoKind::Video,
+                    raw: Value::Null,
+                };
+                reached |= collect_video(&mut videos, &mut next_offset, video, date, offset);
+            }
+
+            // 返回数量不足一页时说明已经没有更多视频
+            let has_more = vlist.len() >= PAGE_SIZE as usize;
+            if offset.is_none() || reached || !has_more {
+                break;
+            }
+        }
+
+        Ok((videos, next_offset.to_string()))
+    }
+
+    fn resolve_media(
+        &self,
+        asset: &Asset,
+        video: &NewestVideo,
+        cookies: Option<&str>,
+    ) -> Result<MediaTask, VpmError> {
+        let page_url = format!("{}{}", VIDEO_PAGE, video.id);
+        let html = throttle::request("ixigua", || {
+            Ok(http::client("ixigua")?
+                .get(&page_url)
+                .header(REFERER, VIDEO_PAGE)
+                .header(COOKIE, cookies.unwrap_or(""))
+                .send()?
+                .error_for_status()?
+                .text()?)
+        })?;
+
+        // 页面中的 SSR 数据包含 undefined，需要替换为 null 才能解析
+        let data = html
+            .split(SSR_DATA_PREFIX)
+            .nth(1)
+            .and_then(|rest| rest.split("</script>").next())
+            .ok_or_else(|| VpmError::Parse("missing ssr data in video page".to_string()))?
+            .replace(":undefined", ":null");
+        let data: Value = serde_json::from_str(&data)?;
+        let resource = &data["anyVideo"]["gidInformation"]["packerData"]["video"]["videoResource"];
+        let referer = Some(page_url.clone());
+        let prefer_h265 = prefer_h265(asset);
+
+        // normal 为音视频合一的流
+        if let Some(list) = resource["normal"]["video_list"].as_object() {
+            if let Some(url) = best_stream(list.values(), prefer_h265).and_then(decode_url) {
+                return Ok(MediaTask {
+                    files: vec![MediaFile {
+                        url,
+                        suffix: "mp4".to_string(),
+                        referer,
+                    }],
+                    merge: None,
+                });
+            }
+        }
+
+        // dash 为音视频分离的流，需要合并
+        let dash = &resource["dash"]["dynamic_video"];
+        let video_url = dash["dynamic_video_list"]
+            .as_array()
+            .and_then(|list| best_stream(list.iter(), prefer_h265))
+            .and_then(decode_url)
+            .ok_or_else(|| VpmError::Parse("missing field `videoResource`".to_string()))?;
+        let audio_url = dash["dynamic_audio_list"]
+            .as_array()
+            .and_then(|list| {
+                list.iter()
+                    .max_by_key(|s| s["bitrate"].as_u64().unwrap_or(0))
+            })
+            .and_then(decode_url);
+
+        let mut files = vec![MediaFile {
+            url: video_url,
+            suffix: "video.mp4".to_string(),
+            referer: referer.clone(),
+        }];
+        if let Some(audio_url) = audio_url {
+            files.push(MediaFile {
+                url: audio_url,
+                suffix: "audio.m4a".to_string(),
+                referer,
+            });
+        }
+        Ok(MediaTask {
+            files,
+            merge: Some(asset.container.clone().unwrap_or("mp4".to_string())),
+        })
+    }
+}
+
+// ================================================================================================
+// Download
+// ================================================================================================
+
+/// 西瓜视频的播放地址使用 base64 编码
+fn decode_url(stream: &Value) -> Option<String> {
+    let encoded = stream["main_url"]
+        .as_str()
+        .or_else(|| stream["backup_url_1"].as_str())?;
+    let decoded = base64::engine::general_purpose::STANDARD
+        .decode(encoded)
+        .ok()?;
+    String::from_utf8(decoded).ok()
+}
+
+/// 选择分辨率和码率最高的流，设置了编码时优先该编码
+fn best_stream<'a>(
+    streams: impl Iterator<Item = &'a Value>,
+    prefer_h265: Option<bool>,
+) -> Option<&'a Value> {
+    streams.max_by_key(|s| {
+        let is_h265 = s["codec_type"].as_str() == Some("h265");
+        (
+            prefer_h265.is_some_and(|prefer| prefer == is_h265),
+            s["vheight"].as_u64().unwrap_or(0),
+            s["bitrate"].as_u64().unwrap_or(0),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::IXigua;
+    use crate::platform::Platform;
+    use url::Url;
+
+    // (链接, 作者 id)
+    const LINKS: [(&str, Option<&str>); 6] = [
+        (
+            "https://www.ixigua.com/home/2497727299858013",
+            Some("2497727299858013"),
+        ),
+        (
+            "https://www.ixigua.com/home/2497727299858013/",
+            Some("2497727299858013"),
+        ),
+        (
+            "https://m.ixigua.com/home/2497727299858013/video/?x=1",
+            Some("2497727299858013"),
+        ),
+        ("https://www.ixigua.com/home/", None),
+        ("https://www.ixigua.com/home/abc/", None),
+        ("https://www.ixigua.com/7300000000000000000", None),
+    ];
+
+    #[test]
+    fn user_id() {
+        for (link, id) in LINKS {
+            let url = Url::parse(link).unwrap();
+            assert_eq!(IXigua.user_id(&url).as_deref(), id, "{}", link);
+        }
+    }
+}
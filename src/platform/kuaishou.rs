@@ -0,0 +1,375 @@
+use crate::config_helper::Asset;
+use crate::downloader::{prefer_h265, MediaFile, MediaTask};
+use crate::error::VpmError;
+use crate::http;
+use crate::live::{LiveStatus, LiveStream};
+use crate::monitor::{
+    collect_video, field_str, field_u64, parse_offset, send_json, NewestVideo, VideoKind,
+};
+use crate::platform::{Capabilities, Defaults, Platform};
+use crate::throttle;
+use reqwest::header::{CONTENT_TYPE, COOKIE, REFERER};
+use serde_json::Value;
+use url::Url;
+
+const MONITOR_API: &str = "https://www.kuaishou.com/graphql";
+const PROFILE_REFERER: &str = "https://www.kuaishou.com/profile/";
+const VIDEO_REFERER: &str = "https://www.kuaishou.com/";
+const LIVE_API: &str = "https://live.kuaishou.com/live_api/liveroom/livedetail";
+const LIVE_REFERER: &str = "https://live.kuaishou.com/";
+// default requests per minute, the graphql api asks for a captcha quickly
+const DEFAULT_RATE: f64 = 10.0;
+
+pub(super) struct Kuaishou;
+impl Platform for Kuaishou {
+    fn name(&self) -> &'static str {
+        "kuaishou"
+    }
+
+    fn hosts(&self) -> &'static [&'static str] {
+        &[
+            "www.kuaishou.com",
+            "kuaishou.com",
+            "live.kuaishou.com",
+            "m.gifshow.com",
+            "v.m.chenzhongtech.com",
+        ]
+    }
+
+    fn short_hosts(&self) -> &'static [&'static str] {
+        &["v.kuaishou.com"]
+    }
+
+    fn user_id(&self, url: &Url) -> Option<String> {
+        // e.g. https://www.kuaishou.com/profile/3xxcvi49q2r52gu
+        // e.g. https://live.kuaishou.com/u/3xxcvi49q2r52gu
+        // e.g. https://v.m.chenzhongtech.com/fw/user/3xxcvi49q2r52gu
+        let segments = url
+            .path_segments()?
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<_>>();
+        match segments[..] {
+            ["profile", id, ..] | ["u", id, ..] | ["fw", "user", id, ..] => Some(id.to_string()),
+            _ => None,
+        }
+    }
+
+    fn profile_url(&self, id: &str) -> String {
+        format!("{}{}", PROFILE_REFERER, id)
+    }
+
+    fn cookie_domain(&self) -> &'static str {
+        "kuaishou.com"
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            videos: true,
+            live: true,
+            download: true,
+        }
+    }
+
+    fn defaults(&self) -> Defaults {
+        Defaults {
+            rate: Some(DEFAULT_RATE),
+            ..Defaults::default()
+        }
+    }
+
+    fn newest_videos(
+        &self,
+        id: &str,
+        cookies: Option<&str>,
+        offset: Option<&str>,
+        max_pages: u32,
+    ) -> Result<(Vec<NewestVideo>, String), VpmError> {
+        let body = r#"
+{
+    "operationName": "visionProfilePhotoList",
+    "variables": {
+        "userId": "{}",
+        "pcursor": "{pcursor}",
+        "page": "profile"
+    },
+    "query": "fragment photoContent on PhotoEntity {\n  __typename\n  id\n  duration\n  caption\n  originCaption\n  likeCount\n  viewCount\n  commentCount\n  realLikeCount\n  coverUrl\n  photoUrl\n  photoH265Url\n  manifest\n  manifestH265\n  videoResource\n  coverUrls {\n    url\n    __typename\n  }\n  timestamp\n  expTag\n  animatedCoverUrl\n  distance\n  videoRatio\n  liked\n  stereoType\n  profileUserTopPhoto\n  musicBlocked\n  riskTagContent\n  riskTagUrl\n}\n\nfragment recoPhotoFragment on recoPhotoEntity {\n  __typename\n  id\n  duration\n  caption\n  originCaption\n  likeCount\n  viewCount\n  commentCount\n  realLikeCount\n  coverUrl\n  photoUrl\n  photoH265Url\n  manifest\n  manifestH265\n  videoResource\n  coverUrls {\n    url\n    __typename\n  }\n  timestamp\n  expTag\n  animatedCoverUrl\n  distance\n  videoRatio\n  liked\n  stereoType\n  profileUserTopPhoto\n  musicBlocked\n  riskTagContent\n  riskTagUrl\n}\n\nfragment feedContent on Feed {\n  type\n  author {\n    id\n    name\n    headerUrl\n    following\n    headerUrls {\n      url\n      __typename\n    }\n    __typename\n  }\n  photo {\n    ...photoContent\n    ...recoPhotoFragment\n    __typename\n  }\n  canAddComment\n  llsid\n  status\n  currentPcursor\n  tags {\n    type\n    name\n    __typename\n  }\n  __typename\n}\n\nquery visionProfilePhotoList($pcursor: String, $userId: String, $page: String, $webPageArea: String) {\n  visionProfilePhotoList(pcursor: $pcursor, userId: $userId, page: $page, webPageArea: $webPageArea) {\n    result\n    llsid\n    webPageArea\n    feeds {\n      ...feedContent\n      __typename\n    }\n    hostName\n    pcursor\n    __typename\n  }\n}\n"
+}
+        "#;
+
+        let offset = parse_offset(offset)?;
+        let mut videos = Vec::new();
+        let mut next_offset: u64 = 0;
+        let mut pcursor = String::new();
+
+        for _ in 0..max_pages {
+            let json = throttle::request(self.name(), || {
+                let json = send_json(
+                    http::client(self.name())?
+                        .post(MONITOR_API)
+                        .header("referer", format!("{}{}", PROFILE_REFERER, id))
+                        .header(COOKIE, cookies.unwrap_or(""))
+                        .header(CONTENT_TYPE, "application/json")
+                        .body(body.replace("{}", id).replace("{pcursor}", &pcursor)),
+                )?;
+                self.check_code(&json)?;
+                Ok(json)
+            })?;
+
+            // data -> visionProfilePhotoList -> feeds
+            let vlist = json["data"]["visionProfilePhotoList"]["feeds"]
+                .as_array()
+                .ok_or_else(|| {
+                    VpmError::Parse("missing field `data.visionProfilePhotoList.feeds`".to_string())
+                })?;
+            let mut reached = false;
+            for video in vlist {
+                let id = field_str(video, "/photo/id")?;
+                let title = field_str(video, "/photo/caption")?;
+                let url = format!("https://www.kuaishou.com/short-video/{}", id);
+                let date = field_u64(video, "/photo/timestamp")?;
+                // 置顶视频不按发布时间排列
+                let pinned = video
+                    .pointer("/photo/profileUserTopPhoto")
+                    .and_then(|v| v.as_bool())
+                    .unwrap_or(false);
+
+                let video = NewestVideo {
+                    id: id.to_string(),
+                    title: title.to_string(),
+                    url,
+                    date: date.to_string(),
+                    is_new: true,
+                    kind: VideoKind::Video,
+                    raw: video.clone(),
+                };
+                reached |=
+                    collect_video(&mut videos, &mut next_offset, video, date, offset) && !pinned;
+            }
+
+            // data -> visionProfilePhotoList -> pcursor，没有更多时为 no_more
+            pcursor = json["data"]["visionProfilePhotoList"]["pcursor"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string();
+            let has_more = !pcursor.is_empty() && pcursor != "no_more";
+            if offset.is_none() || reached || !has_more {
+                break;
+            }
+        }
+
+        Ok((videos, next_offset.to_string()))
+    }
+
+    fn live_status(&self, id: &str, cookies: Option<&str>) -> Result<LiveStatus, VpmError> {
+        let json = throttle::request("kuaishou", || {
+            let json = send_json(
+                http::client("kuaishou")?
+                    .get(LIVE_API)
+                    .query(&[("principalId", id)])
+                    .header(REFERER, format!("{}u/{}", LIVE_REFERER, id))
+                    .header(COOKIE, cookies.unwrap_or("")),
+            )?;
+            if let Some(code) = json["data"]["result"].as_i64().filter(|code| *code != 1) {
+                return Err(VpmError::Api {
+                    platform: "kuaishou",
+                    code,
+                    message: json.to_string(),
+                });
+            }
+            Ok(json)
+        })?;
+
+        let data = &json["data"];
+
+        let live_stream = &data["liveStream"];
+        let living = data["isLiving"]
+            .as_bool()
+            .or_else(|| live_stream["isLiving"].as_bool())
+            .unwrap_or_else(|| !stream_urls(&live_stream["playUrls"]).is_empty());
+        Ok(LiveStatus {
+            living,
+            title: live_stream["caption"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+            room_url: format!("{}u/{}", LIVE_REFERER, id),
+            room_id: live_stream["id"].as_str().unwrap_or_default().to_string(),
+            raw: live_stream.clone(),
+        })
+    }
+
+    fn live_stream(
+        &self,
+        status: &LiveStatus,
+        _cookies: Option<&str>,
+    ) -> Result<LiveStream, VpmError> {
+        let url = stream_urls(&status.raw["playUrls"])
+            .into_iter()
+            .max_by_key(|stream| stream["bitrate"].as_u64().unwrap_or(0))
+            .and_then(|stream| stream["url"].as_str())
+            .ok_or_else(|| VpmError::Parse("missing field `liveStream.playUrls`".to_string()))?;
+        Ok(LiveStream {
+            url: url.to_string(),
+            referer: LIVE_REFERER.to_string(),
+        })
+    }
+
+    fn resolve_media(
+        &self,
+        asset: &Asset,
+        video: &NewestVideo,
+        _cookies: Option<&str>,
+    ) -> Result<MediaTask, VpmError> {
+        let photo = &video.raw["photo"];
+        if photo.is_null() {
+            return Err(VpmError::Parse("missing photo detail".to_string()));
+        }
+
+        let non_empty = |value: &Value| {
+            value
+                .as_str()
+                .filter(|url| !url.is_empty())
+                .map(|url| url.to_string())
+        };
+        // manifest 可能为 JSON 字符串
+        let manifest_of = |value: &Value| match value {
+            Value::String(text) => serde_json::from_str::<Value>(text).ok(),
+            Value::Object(_) => Some(value.clone()),
+            _ => None,
+        };
+        let h264 = || {
+            manifest_of(&photo["manifest"])
+                .and_then(|m| best_representation(&m))
+                .or_else(|| non_empty(&photo["photoUrl"]))
+        };
+        let h265 = || {
+            manifest_of(&photo["manifestH265"])
+                .and_then(|m| best_representation(&m))
+                .or_else(|| non_empty(&photo["photoH265Url"]))
+        };
+
+        let url = if prefer_h265(asset) == Some(true) {
+            h265().or_else(h264)
+        } else {
+            h264().or_else(h265)
+        }
+        .ok_or_else(|| VpmError::Parse("missing field `photo.photoUrl`".to_string()))?;
+
+        Ok(MediaTask {
+            files: vec![MediaFile {
+                url,
+                suffix: "mp4".to_string(),
+                referer: Some(VIDEO_REFERER.to_string()),
+            }],
+            merge: None,
+        })
+    }
+}
+
+impl Kuaishou {
+    /// 检查接口返回的 result
+    fn check_code(&self, json: &Value) -> Result<(), VpmError> {
+        // data -> visionProfilePhotoList -> result
+        match json["data"]["visionProfilePhotoList"]["result"].as_i64() {
+            Some(1) => Ok(()),
+            // result 为 2 时需要滑块验证
+            Some(2) => Err(VpmError::RiskControl {
+                platform: "kuaishou",
+                code: 2,
+                message: "captcha required".to_string(),
+            }),
+            Some(code) => Err(VpmError::Api {
+                platform: "kuaishou",
+                code,
+                message: json.to_string(),
+            }),
+            None => Err(VpmError::Parse(
+                "missing field `data.visionProfilePhotoList.result`".to_string(),
+            )),
+        }
+    }
+}
+
+// ================================================================================================
+// Live
+// ================================================================================================
+
+/// 收集 playUrls 中所有带有 url 的流
+fn stream_urls(value: &Value) -> Vec<&Value> {
+    match value {
+        Value::Object(map) if map.get("url").is_some_and(|url| url.is_string()) => vec![value],
+        Value::Object(map) => map.values().flat_map(stream_urls).collect(),
+        Value::Array(list) => list.iter().flat_map(stream_urls).collect(),
+        _ => vec![],
+    }
+}
+
+// ================================================================================================
+// Download
+// ================================================================================================
+
+/// 在 manifest 的 adaptationSet 中选择分辨率和码率最高的 representation
+fn best_representation(manifest: &Value) -> Option<String> {
+    manifest["adaptationSet"]
+        .as_array()?
+        .iter()
+        .filter_map(|set| set["representation"].as_array())
+        .flatten()
+        .filter(|r| r["url"].as_str().is_some_and(|url| !url.is_empty()))
+        .max_by_key(|r| {
+            let width = r["width"].as_u64().unwrap_or(0);
+            let height = r["height"].as_u64().unwrap_or(0);
+            let bitrate = r["avgBitrate"]
+                .as_u64()
+                .or_else(|| r["maxBitrate"].as_u64())
+                .unwrap_or(0);
+            (width * height, bitrate)
+        })
+        .and_then(|r| r["url"].as_str())
+        .map(|url| url.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Kuaishou;
+    use crate::platform::Platform;
+    use url::Url;
+
+    // (链接, 作者 id)
+    const LINKS: [(&str, Option<&str>); 8] = [
+        (
+            "https://www.kuaishou.com/profile/3xxcvi49q2r52gu",
+            Some("3xxcvi49q2r52gu"),
+        ),
+        (
+            "https://www.kuaishou.com/profile/3xxcvi49q2r52gu/",
+            Some("3xxcvi49q2r52gu"),
+        ),
+        (
+            "https://kuaishou.com/profile/3xxcvi49q2r52gu?source=x",
+            Some("3xxcvi49q2r52gu"),
+        ),
+        (
+            "https://live.kuaishou.com/u/3xxcvi49q2r52gu",
+            Some("3xxcvi49q2r52gu"),
+        ),
+        (
+            "https://v.m.chenzhongtech.com/fw/user/3xxcvi49q2r52gu",
+            Some("3xxcvi49q2r52gu"),
+        ),
+        (
+            "https://m.gifshow.com/fw/user/3xxcvi49q2r52gu?cc=share",
+            Some("3xxcvi49q2r52gu"),
+        ),
+        ("https://www.kuaishou.com/profile/", None),
+        ("https://www.kuaishou.com/short-video/3x8example", None),
+    ];
+
+    #[test]
+    fn user_id() {
+        for (link, id) in LINKS {
+            let url = Url::parse(link).unwrap();
+            assert_eq!(Kuaishou.user_id(&url).as_deref(), id, "{}", link);
+        }
+    }
+}
@@ -0,0 +1,412 @@
+use crate::config_helper::Asset;
+use crate::downloader::MediaTask;
+use crate::error::VpmError;
+use crate::http;
+use crate::live::{LiveStatus, LiveStream};
+use crate::monitor::NewestVideo;
+use crate::throttle;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use toml::Table;
+use url::Url;
+
+mod bilibili;
+mod douyin;
+mod ixigua;
+mod kuaishou;
+#[cfg(feature = "quickjs")]
+mod script;
+mod youtube;
+
+// default directory of scripted monitors, relative to the config file
+const DEFAULT_SCRIPTS_DIR: &str = "monitors";
+// how long a link that failed to resolve is not requested again
+const RETRY_UNRESOLVED: Duration = Duration::from_secs(600);
+
+// 已支持的平台，新增平台时在此注册
+static PLATFORMS: [&dyn Platform; 5] = [
+    &bilibili::Bilibili,
+    &kuaishou::Kuaishou,
+    &ixigua::IXigua,
+    &douyin::Douyin,
+    &youtube::YouTube,
+];
+
+// 从 `monitors/` 目录加载的脚本平台，排在内置平台之后
+static SCRIPTS: OnceLock<Vec<&'static dyn Platform>> = OnceLock::new();
+
+/// 平台支持的功能
+#[derive(Debug, Clone, Copy)]
+pub struct Capabilities {
+    // 获取作者的最新视频
+    pub videos: bool,
+    // 检查直播状态和录制直播
+    pub live: bool,
+    // 下载视频
+    pub download: bool,
+}
+
+/// 平台的默认设置，配置文件中的设置优先
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Defaults {
+    // 检查间隔（秒）
+    pub interval: Option<u64>,
+    // 每分钟的请求数
+    pub rate: Option<f64>,
+}
+
+/// 一个视频平台：声明其处理的链接、cookies 的键和域名、默认设置与支持的功能
+pub trait Platform: Sync {
+    /// 平台名称，同时是 cookies、限流、HTTP 等配置中的键
+    fn name(&self) -> &'static str;
+
+    /// 应用配置文件中 `[platforms.<名称>]` 的设置，没有设置时为 None，每次读取配置后调用
+    fn configure(&self, _settings: Option<&Table>) -> Result<(), VpmError> {
+        Ok(())
+    }
+
+    /// 作者主页链接的 host，包括移动版和分享页
+    fn hosts(&self) -> &'static [&'static str];
+
+    /// 短链接的 host，需要跟随重定向才能得到作者主页链接
+    fn short_hosts(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    /// 从作者主页链接中解析作者在平台上的 id
+    fn user_id(&self, url: &Url) -> Option<String>;
+
+    /// 链接中没有作者 id 时（如 YouTube 的 `@handle`），请求平台查询作者 id
+    fn lookup_user_id(&self, _url: &Url) -> Result<Option<String>, VpmError> {
+        Ok(None)
+    }
+
+    /// 作者 id 对应的规范主页链接
+    fn profile_url(&self, id: &str) -> String;
+
+    /// cookies 所属的域名，用于从浏览器导入 cookies
+    fn cookie_domain(&self) -> &'static str;
+
+    fn capabilities(&self) -> Capabilities;
+
+    fn defaults(&self) -> Defaults {
+        Defaults::default()
+    }
+
+    /// 链接是否由该平台处理
+    fn matches(&self, url: &Url) -> bool {
+        url.host_str()
+            .is_some_and(|host| self.hosts().contains(&host) || self.short_hosts().contains(&host))
+    }
+
+    /// 获取作者的最新视频
+    ///
+    /// 有偏移量时会持续翻页，直到回溯到偏移量或达到 `max_pages` 页；没有偏移量时只获取第一页
+    fn newest_videos(
+        &self,
+        id: &str,
+        cookies: Option<&str>,
+        offset: Option<&str>,
+        max_pages: u32,
+    ) -> Result<(Vec<NewestVideo>, String), VpmError>;
+
+    /// 获取作者的直播状态
+    fn live_status(&self, _id: &str, _cookies: Option<&str>) -> Result<LiveStatus, VpmError> {
+        Err(self.unsupported("live"))
+    }
+
+    /// 获取直播流地址
+    fn live_stream(
+        &self,
+        _status: &LiveStatus,
+        _cookies: Option<&str>,
+    ) -> Result<LiveStream, VpmError> {
+        Err(self.unsupported("live"))
+    }
+
+    /// 解析视频的实际媒体地址
+    fn resolve_media(
+        &self,
+        _asset: &Asset,
+        _video: &NewestVideo,
+        _cookies: Option<&str>,
+    ) -> Result<MediaTask, VpmError> {
+        Err(self.unsupported("download"))
+    }
+
+    fn unsupported(&self, feature: &'static str) -> VpmError {
+        VpmError::Unsupported {
+            platform: self.name(),
+            feature,
+        }
+    }
+}
+
+/// 所有已注册的平台，包括脚本平台
+pub fn all() -> impl Iterator<Item = &'static dyn Platform> {
+    PLATFORMS
+        .iter()
+        .chain(SCRIPTS.get().into_iter().flatten())
+        .copied()
+}
+
+/// 根据名称获取平台
+pub fn get(name: &str) -> Option<&'static dyn Platform> {
+    all().find(|p| p.name() == name)
+}
+
+/// 把配置文件中 `[platforms]` 的设置交给各平台，设置了不存在的平台时返回错误
+pub fn configure(settings: Option<&HashMap<String, Table>>) -> Result<(), VpmError> {
+    if let Some(name) = settings
+        .into_iter()
+        .flat_map(|settings| settings.keys())
+        .find(|name| get(name).is_none())
+    {
+        return Err(VpmError::Config(format!(
+            "unknown platform `{}` in [platforms]",
+            name
+        )));
+    }
+    for platform in all() {
+        platform.configure(settings.and_then(|settings| settings.get(platform.name())))?;
+    }
+    Ok(())
+}
+
+/// 获取处理该链接的平台
+pub fn find(url: &Url) -> Result<&'static dyn Platform, VpmError> {
+    all()
+        .find(|p| p.matches(url))
+        .ok_or_else(|| VpmError::UnsupportedUrl(url.to_string()))
+}
+
+/// 作者：所属平台及在平台上的 id
+#[derive(Clone)]
+pub struct Author {
+    pub platform: &'static dyn Platform,
+    pub id: String,
+}
+
+impl Author {
+    /// 规范的作者主页链接
+    pub fn url(&self) -> String {
+        self.platform.profile_url(&self.id)
+    }
+
+    /// `平台:作者 id`，同时是资产的默认标识
+    pub fn key(&self) -> String {
+        format!("{}:{}", self.platform.name(), self.id)
+    }
+}
+
+// 已解析的链接，短链接只需跟随一次重定向
+static RESOLVED: OnceLock<Mutex<HashMap<String, Author>>> = OnceLock::new();
+// 解析失败的链接、原因及失败的时间，一段时间内不再重复请求
+static FAILED: OnceLock<Mutex<HashMap<String, (String, Instant)>>> = OnceLock::new();
+
+/// 将资产链接解析为平台和作者 id
+///
+/// 支持作者主页、移动版和分享页链接，短链接会跟随重定向解析，不含作者 id 的链接会请求平台查询，
+/// 省略 `https://` 的链接也可以识别。
+/// 也可以直接使用 `平台:id` 的形式，如 `bilibili:1344420936`
+///
+/// 解析结果会被缓存，失败的结果保留 [`RETRY_UNRESOLVED`]，watch 模式下不会每次唤醒都重新请求
+pub fn resolve(link: &str) -> Result<Author, VpmError> {
+    resolve_with(link, true)
+}
+
+/// 不发送请求解析资产链接，需要跟随重定向或查询平台且尚未解析过的链接返回 None
+pub fn resolve_local(link: &str) -> Option<Author> {
+    resolve_with(link, false).ok()
+}
+
+/// 记录之前解析出的作者，如状态文件中保存的作者，之后解析该链接时不再请求平台
+pub fn remember(link: &str, key: &str) {
+    let Ok(author) = parse(key, false) else {
+        return;
+    };
+    RESOLVED
+        .get_or_init(|| Mutex::new(HashMap::new()))
+        .lock()
+        .unwrap()
+        .entry(link.trim().to_string())
+        .or_insert(author);
+}
+
+fn resolve_with(link: &str, online: bool) -> Result<Author, VpmError> {
+    let link = link.trim();
+    let resolved = RESOLVED.get_or_init(|| Mutex::new(HashMap::new()));
+    let failed = FAILED.get_or_init(|| Mutex::new(HashMap::new()));
+    if let Some(author) = resolved.lock().unwrap().get(link) {
+        return Ok(author.clone());
+    }
+    if let Some((reason, time)) = failed.lock().unwrap().get(link) {
+        if time.elapsed() < RETRY_UNRESOLVED {
+            return Err(VpmError::Unresolved {
+                link: link.to_string(),
+                reason: reason.clone(),
+            });
+        }
+    }
+
+    match parse(link, online) {
+        Ok(author) => {
+            resolved
+                .lock()
+                .unwrap()
+                .insert(link.to_string(), author.clone());
+            Ok(author)
+        }
+        Err(err) => {
+            if online {
+                failed
+                    .lock()
+                    .unwrap()
+                    .insert(link.to_string(), (err.to_string(), Instant::now()));
+            }
+            Err(err)
+        }
+    }
+}
+
+/// 解析资产链接，`online` 为 false 时不跟随短链接，也不向平台查询作者 id
+fn parse(link: &str, online: bool) -> Result<Author, VpmError> {
+    let raw_id = link
+        .split_once(':')
+        .filter(|(_, id)| !id.starts_with("//"))
+        .and_then(|(name, id)| Some((get(name)?, id)));
+    if let Some((platform, id)) = raw_id {
+        // 以 id 拼出主页链接再解析，确认 id 的格式正确
+        let parsed = Url::parse(&platform.profile_url(id))
+            .ok()
+            .and_then(|url| platform.user_id(&url));
+        if parsed.as_deref() != Some(id) {
+            return Err(VpmError::UnsupportedUrl(link.to_string()));
+        }
+        return Ok(Author {
+            platform,
+            id: id.to_string(),
+        });
+    }
+
+    let url = match Url::parse(link) {
+        Err(url::ParseError::RelativeUrlWithoutBase) => Url::parse(&format!("https://{}", link))?,
+        url => url?,
+    };
+    let platform = find(&url)?;
+    let is_short = url
+        .host_str()
+        .is_some_and(|host| platform.short_hosts().contains(&host));
+    let url = match is_short {
+        true if !online => return Err(VpmError::UnsupportedUrl(link.to_string())),
+        true => follow(platform, &url)?,
+        false => url,
+    };
+    let id = match platform.user_id(&url) {
+        Some(id) => Some(id),
+        None if online => platform.lookup_user_id(&url)?,
+        None => None,
+    };
+    let id =
+        id.ok_or_else(|| VpmError::UnsupportedUrl(format!("{} is not an author link", url)))?;
+    Ok(Author { platform, id })
+}
+
+/// 跟随短链接的重定向，返回最终的链接
+fn follow(platform: &'static dyn Platform, url: &Url) -> Result<Url, VpmError> {
+    throttle::request(platform.name(), || {
+        let response = http::client(platform.name())?.get(url.as_str()).send()?;
+        Ok(response.url().clone())
+    })
+}
+
+/// 脚本目录的默认路径：配置文件所在目录下的 monitors
+pub fn scripts_dir(config_path: &str) -> PathBuf {
+    Path::new(config_path).with_file_name(DEFAULT_SCRIPTS_DIR)
+}
+
+/// 加载目录中的 `*.js` 脚本平台，只在启动时调用一次
+///
+/// 返回无法加载的脚本及原因，其余脚本照常注册
+pub fn load_scripts(dir: &Path) -> Vec<String> {
+    let mut paths = std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| path.extension().is_some_and(|ext| ext == "js"))
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    paths.sort();
+
+    let mut scripts: Vec<&'static dyn Platform> = Vec::new();
+    let mut problems = Vec::new();
+    for path in paths {
+        match load_script(&path) {
+            Ok(script)
+                if get(script.name()).is_some()
+                    || scripts.iter().any(|s| s.name() == script.name()) =>
+            {
+                problems.push(format!(
+                    "{}: platform `{}` already exists",
+                    path.display(),
+                    script.name()
+                ));
+            }
+            Ok(script) => scripts.push(script),
+            Err(err) => problems.push(format!("{}: {}", path.display(), err)),
+        }
+    }
+    let _ = SCRIPTS.set(scripts);
+    problems
+}
+
+#[cfg(feature = "quickjs")]
+fn load_script(path: &Path) -> Result<&'static dyn Platform, VpmError> {
+    Ok(Box::leak(Box::new(script::Script::load(path)?)))
+}
+
+#[cfg(not(feature = "quickjs"))]
+fn load_script(_path: &Path) -> Result<&'static dyn Platform, VpmError> {
+    Err(VpmError::Config(
+        "scripted monitors require building with the `quickjs` feature".to_string(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::resolve;
+
+    // (链接, 平台:作者 id)，只包含不需要请求平台的链接
+    const LINKS: [(&str, Option<&str>); 10] = [
+        ("bilibili:1344420936", Some("bilibili:1344420936")),
+        ("bilibili:abc", None),
+        ("kuaishou:3xxcvi49q2r52gu", Some("kuaishou:3xxcvi49q2r52gu")),
+        (
+            "youtube:UCuAXFkgsw1L7xaCfnd5JJOw",
+            Some("youtube:UCuAXFkgsw1L7xaCfnd5JJOw"),
+        ),
+        ("unknown:123", None),
+        ("space.bilibili.com/1344420936", Some("bilibili:1344420936")),
+        (
+            " https://www.ixigua.com/home/2497727299858013/ ",
+            Some("ixigua:2497727299858013"),
+        ),
+        (
+            "https://www.iesdouyin.com/share/user/MS4wLjABAAAA",
+            Some("douyin:MS4wLjABAAAA"),
+        ),
+        ("https://www.bilibili.com/video/BV1xx411c7mD", None),
+        ("https://example.com/user/1", None),
+    ];
+
+    #[test]
+    fn resolve_links() {
+        for (link, expected) in LINKS {
+            let resolved = resolve(link).ok().map(|author| author.key());
+            assert_eq!(resolved.as_deref(), expected, "{}", link);
+        }
+    }
+}
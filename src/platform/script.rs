@@ -0,0 +1,374 @@
+use crate::error::VpmError;
+use crate::http;
+use crate::monitor::{collect_video, parse_offset, NewestVideo, VideoKind};
+use crate::platform::{Capabilities, Defaults, Platform};
+use crate::throttle;
+use quick_js::{Context, ExecutionError};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+use url::Url;
+
+// memory limit of a script context in bytes
+const MEMORY_LIMIT: usize = 64 * 1024 * 1024;
+// time limit of a script call in seconds, including its http requests
+const TIME_LIMIT: u64 = 120;
+// maximum number of http requests in a script call
+const MAX_REQUESTS: usize = 20;
+
+// 脚本中可用的 http 绑定，请求和响应以 JSON 字符串在 Rust 与 JavaScript 之间传递
+const PRELUDE: &str = r#"
+var http = (function () {
+  function request(method, url, body, headers) {
+    var response = JSON.parse(__vpm_http(method, String(url), JSON.stringify(headers || {}), body));
+    response.json = function () {
+      return JSON.parse(this.body);
+    };
+    return response;
+  }
+  return {
+    get: function (url, headers) {
+      return request("GET", url, "", headers);
+    },
+    post: function (url, body, headers) {
+      return request("POST", url, typeof body === "string" ? body : JSON.stringify(body), headers);
+    },
+  };
+})();
+"#;
+
+/// `monitors/` 目录中的脚本定义的平台
+pub(super) struct Script {
+    name: &'static str,
+    hosts: &'static [&'static str],
+    cookie_domain: &'static str,
+    // 脚本定义了 monitor.match 时，在 host 匹配后再由脚本判断
+    has_match: bool,
+    defaults: Defaults,
+    source: String,
+    // 脚本曾经超时，超时的线程无法终止，之后不再执行该脚本
+    timed_out: AtomicBool,
+}
+
+impl Script {
+    /// 读取并执行脚本，获取 `monitor` 对象中的声明
+    ///
+    /// 名称默认为文件名，`hosts` 为必填项
+    pub(super) fn load(path: &Path) -> Result<Script, VpmError> {
+        let source = std::fs::read_to_string(path)
+            .map_err(|err| VpmError::Io(format!("{}: {}", path.display(), err)))?;
+        let stem = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        let declared = eval(
+            "script",
+            &source,
+            r#"JSON.stringify({
+                monitor: typeof monitor === "object" ? monitor : null,
+                hasFetch: typeof fetch === "function",
+                hasMatch: typeof monitor === "object" && typeof monitor.match === "function",
+            })"#,
+        )
+        .unwrap_or_else(|| Err(timed_out(&stem)))?;
+        let declared: Value = serde_json::from_str(&declared)?;
+        let monitor = &declared["monitor"];
+        if monitor.is_null() {
+            return Err(VpmError::Config("missing `monitor` object".to_string()));
+        }
+        if declared["hasFetch"] != Value::Bool(true) {
+            return Err(VpmError::Config("missing `fetch` function".to_string()));
+        }
+
+        let name = monitor["name"].as_str().unwrap_or(&stem).to_string();
+        if name.is_empty() {
+            return Err(VpmError::Config("missing `monitor.name`".to_string()));
+        }
+        let hosts = monitor["hosts"]
+            .as_array()
+            .map(|hosts| {
+                hosts
+                    .iter()
+                    .filter_map(|host| host.as_str())
+                    .map(leak)
+                    .collect::<Vec<_>>()
+            })
+            .filter(|hosts| !hosts.is_empty())
+            .ok_or_else(|| VpmError::Config("missing `monitor.hosts`".to_string()))?;
+        // 未设置时取第一个 host 去掉 www.
+        let cookie_domain = monitor["cookieDomain"]
+            .as_str()
+            .unwrap_or_else(|| hosts[0].trim_start_matches("www."))
+            .to_string();
+
+        Ok(Script {
+            name: leak(&name),
+            hosts: Box::leak(hosts.into_boxed_slice()),
+            cookie_domain: leak(&cookie_domain),
+            has_match: declared["hasMatch"] == Value::Bool(true),
+            defaults: Defaults {
+                interval: monitor["interval"].as_u64(),
+                rate: monitor["rate"].as_f64(),
+            },
+            source,
+            timed_out: AtomicBool::new(false),
+        })
+    }
+
+    /// 执行脚本后再执行 `code`，超时后该脚本不再执行
+    fn run(&self, code: &str) -> Result<String, VpmError> {
+        if self.timed_out.load(Ordering::SeqCst) {
+            return Err(VpmError::Config(format!(
+                "script timed out before, restart vpm to run it again (limit {}s)",
+                TIME_LIMIT
+            )));
+        }
+        eval(self.name, &self.source, code).unwrap_or_else(|| {
+            self.timed_out.store(true, Ordering::SeqCst);
+            Err(timed_out(self.name))
+        })
+    }
+}
+
+impl Platform for Script {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn hosts(&self) -> &'static [&'static str] {
+        self.hosts
+    }
+
+    fn cookie_domain(&self) -> &'static str {
+        self.cookie_domain
+    }
+
+    // 脚本平台以完整的链接作为作者 id
+    fn user_id(&self, url: &Url) -> Option<String> {
+        Some(url.to_string())
+    }
+
+    fn profile_url(&self, id: &str) -> String {
+        id.to_string()
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            videos: true,
+            live: false,
+            download: false,
+        }
+    }
+
+    fn defaults(&self) -> Defaults {
+        self.defaults
+    }
+
+    fn matches(&self, url: &Url) -> bool {
+        if !url
+            .host_str()
+            .is_some_and(|host| self.hosts.contains(&host))
+        {
+            return false;
+        }
+        if !self.has_match {
+            return true;
+        }
+        let code = format!("String(!!monitor.match({}))", js_literal(url.as_str()));
+        match self.run(&code) {
+            Ok(matched) => matched == "true",
+            Err(err) => {
+                eprintln!("[{}] monitor.match failed: {}", self.name, err);
+                false
+            }
+        }
+    }
+
+    fn newest_videos(
+        &self,
+        url: &str,
+        cookies: Option<&str>,
+        offset: Option<&str>,
+        _max_pages: u32,
+    ) -> Result<(Vec<NewestVideo>, String), VpmError> {
+        let parsed_offset = parse_offset(offset)?;
+        // fetch(url, cookies, offset)，返回视频数组
+        let code = format!(
+            "JSON.stringify(fetch({}, {}, {}))",
+            js_literal(url),
+            js_literal(cookies.unwrap_or("")),
+            offset.map_or("null".to_string(), js_literal)
+        );
+        let result = self.run(&code)?;
+        let result: Value = serde_json::from_str(&result)?;
+        let list = result
+            .as_array()
+            .ok_or_else(|| VpmError::Parse("fetch must return an array".to_string()))?;
+
+        let mut videos = Vec::new();
+        let mut next_offset: u64 = 0;
+        for video in list {
+            // id 可以是字符串或数字
+            let id = match &video["id"] {
+                Value::String(id) => id.clone(),
+                Value::Number(id) => id.to_string(),
+                _ => return Err(VpmError::Parse("missing field `id`".to_string())),
+            };
+            // 发布时间为毫秒时间戳，不能为负数或超出可表示的时间范围
+            let date = video["date"]
+                .as_u64()
+                .or_else(|| {
+                    let date = video["date"].as_f64()?;
+                    (date.is_finite() && date >= 0.0).then_some(date as u64)
+                })
+                .filter(|date| {
+                    i64::try_from(*date)
+                        .ok()
+                        .and_then(chrono::DateTime::from_timestamp_millis)
+                        .is_some()
+                })
+                .ok_or_else(|| VpmError::Parse(format!("invalid field `date` of {}", id)))?;
+
+            let video = NewestVideo {
+                title: video["title"].as_str().unwrap_or_default().to_string(),
+                url: video["url"].as_str().unwrap_or_default().to_string(),
+                id,
+                date: date.to_string(),
+                is_new: true,
+                kind: VideoKind::Video,
+                raw: video.clone(),
+            };
+            collect_video(&mut videos, &mut next_offset, video, date, parsed_offset);
+        }
+
+        Ok((videos, next_offset.to_string()))
+    }
+}
+
+/// 在新的线程和执行环境中执行脚本，再执行 `code` 并返回其字符串结果
+///
+/// 每次调用都使用新的环境，脚本之间互不影响。环境中没有文件和进程相关的接口，只能通过 http 绑定
+/// 访问 http(s) 地址，请求同样经过限流，每次调用最多 `MAX_REQUESTS` 个请求。
+/// QuickJS 没有暴露中断执行的接口，超过 `TIME_LIMIT` 时放弃等待并返回 None，超时的线程在进程退出时结束
+fn eval(platform: &'static str, source: &str, code: &str) -> Option<Result<String, VpmError>> {
+    let (source, code) = (source.to_string(), code.to_string());
+    let deadline = Instant::now() + Duration::from_secs(TIME_LIMIT);
+    let (sender, receiver) = mpsc::channel();
+    let spawned = std::thread::Builder::new()
+        .name(format!("script-{}", platform))
+        .spawn(move || {
+            let _ = sender.send(eval_in_place(platform, &source, &code, deadline));
+        });
+    if let Err(err) = spawned {
+        return Some(Err(VpmError::Io(err.to_string())));
+    }
+    receiver.recv_timeout(Duration::from_secs(TIME_LIMIT)).ok()
+}
+
+fn timed_out(platform: &str) -> VpmError {
+    VpmError::Config(format!(
+        "script error: {} did not finish in {}s",
+        platform, TIME_LIMIT
+    ))
+}
+
+/// 在当前线程中创建执行环境并执行脚本
+///
+/// QuickJS 以创建环境时的栈位置检查栈溢出，因此环境只在本函数内使用，不返回给调用方
+fn eval_in_place(
+    platform: &'static str,
+    source: &str,
+    code: &str,
+    deadline: Instant,
+) -> Result<String, VpmError> {
+    let context = Context::builder()
+        .memory_limit(MEMORY_LIMIT)
+        .build()
+        .map_err(|err| VpmError::Config(err.to_string()))?;
+    let requests = AtomicUsize::new(0);
+    context
+        .add_callback(
+            "__vpm_http",
+            move |method: String, url: String, headers: String, body: String| {
+                if Instant::now() >= deadline {
+                    return Err(format!("time limit of {}s exceeded", TIME_LIMIT));
+                }
+                if requests.fetch_add(1, Ordering::SeqCst) >= MAX_REQUESTS {
+                    return Err(format!("more than {} http requests", MAX_REQUESTS));
+                }
+                send(platform, &method, &url, &headers, body).map_err(|err| err.to_string())
+            },
+        )
+        .map_err(script_error)?;
+    context.eval(PRELUDE).map_err(script_error)?;
+    context.eval(source).map_err(script_error)?;
+    context.eval_as::<String>(code).map_err(script_error)
+}
+
+/// 执行脚本发起的请求，返回 `{status, body, headers}` 的 JSON
+fn send(
+    platform: &'static str,
+    method: &str,
+    url: &str,
+    headers: &str,
+    body: String,
+) -> Result<String, VpmError> {
+    let url = Url::parse(url)?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(VpmError::UnsupportedUrl(url.to_string()));
+    }
+    let mut header_map = HeaderMap::new();
+    for (name, value) in serde_json::from_str::<HashMap<String, String>>(headers)? {
+        let name = HeaderName::from_bytes(name.as_bytes())
+            .map_err(|err| VpmError::Parse(format!("invalid header {}: {}", name, err)))?;
+        let value = HeaderValue::from_str(&value)
+            .map_err(|err| VpmError::Parse(format!("invalid header {}: {}", name, err)))?;
+        header_map.insert(name, value);
+    }
+
+    throttle::request(platform, || {
+        let client = http::client(platform)?;
+        let request = match method {
+            "POST" => client.post(url.as_str()).body(body.clone()),
+            _ => client.get(url.as_str()),
+        };
+        let response = request.headers(header_map.clone()).send()?;
+        // 暂时性错误交给限流模块重试，其余状态码返回给脚本处理
+        let status = response.status();
+        if status.as_u16() == 429 || status.is_server_error() {
+            return Err(VpmError::HttpStatus {
+                status: status.as_u16(),
+                url: url.to_string(),
+            });
+        }
+        let headers = response
+            .headers()
+            .iter()
+            .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+            .collect::<HashMap<_, _>>();
+        Ok(serde_json::json!({
+            "status": status.as_u16(),
+            "headers": headers,
+            "body": response.text()?,
+        })
+        .to_string())
+    })
+}
+
+/// 将字符串转换为 JavaScript 字面量
+fn js_literal(value: &str) -> String {
+    Value::String(value.to_string()).to_string()
+}
+
+fn leak(value: &str) -> &'static str {
+    Box::leak(value.to_string().into_boxed_str())
+}
+
+fn script_error(err: ExecutionError) -> VpmError {
+    VpmError::Config(format!("script error: {}", err))
+}
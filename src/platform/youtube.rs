@@ -0,0 +1,207 @@
+use crate::error::VpmError;
+use crate::http;
+use crate::monitor::{collect_video, parse_offset, NewestVideo, VideoKind};
+use crate::platform::{Capabilities, Platform};
+use crate::throttle;
+use reqwest::header::COOKIE;
+use roxmltree::{Document, ExpandedName, Node};
+use serde_json::Value;
+use url::Url;
+
+const CHANNEL_PAGE: &str = "https://www.youtube.com/";
+const FEED_API: &str = "https://www.youtube.com/feeds/videos.xml";
+// 跳过欧盟地区的 cookies 同意页面
+const CONSENT_COOKIE: &str = "SOCS=CAI";
+const CANONICAL_PREFIX: &str = "<link rel=\"canonical\" href=\"";
+const YT_NS: &str = "http://www.youtube.com/xml/schemas/2015";
+
+pub(super) struct YouTube;
+impl Platform for YouTube {
+    fn name(&self) -> &'static str {
+        "youtube"
+    }
+
+    fn hosts(&self) -> &'static [&'static str] {
+        &["www.youtube.com", "youtube.com", "m.youtube.com"]
+    }
+
+    fn user_id(&self, url: &Url) -> Option<String> {
+        // e.g. https://www.youtube.com/channel/UCuAXFkgsw1L7xaCfnd5JJOw/videos
+        let mut segments = url.path_segments()?;
+        if segments.next()? != "channel" {
+            return None;
+        }
+        segments
+            .next()
+            .filter(|id| is_channel_id(id))
+            .map(|id| id.to_string())
+    }
+
+    // e.g. https://www.youtube.com/@handle 、https://www.youtube.com/user/name 、https://www.youtube.com/c/name
+    fn lookup_user_id(&self, url: &Url) -> Result<Option<String>, VpmError> {
+        let segments = url
+            .path_segments()
+            .map(|segments| segments.collect::<Vec<_>>())
+            .unwrap_or_default();
+        let path = match segments.as_slice() {
+            [handle, ..] if handle.len() > 1 && handle.starts_with('@') => handle.to_string(),
+            ["user" | "c", name, ..] if !name.is_empty() => segments[..2].join("/"),
+            _ => return Ok(None),
+        };
+
+        // 频道页面的 canonical 链接为 /channel/<频道 id>
+        let page = format!("{}{}", CHANNEL_PAGE, path);
+        let html = throttle::request(self.name(), || {
+            let response = http::client(self.name())?
+                .get(&page)
+                .header(COOKIE, CONSENT_COOKIE)
+                .send()?
+                .error_for_status()?;
+            Ok(response.text()?)
+        })?;
+        let canonical = html
+            .split_once(CANONICAL_PREFIX)
+            .and_then(|(_, rest)| rest.split_once('"'))
+            .and_then(|(href, _)| Url::parse(href).ok())
+            .ok_or_else(|| VpmError::Parse(format!("missing canonical link of {}", page)))?;
+        Ok(self.user_id(&canonical))
+    }
+
+    fn profile_url(&self, id: &str) -> String {
+        format!("{}channel/{}", CHANNEL_PAGE, id)
+    }
+
+    fn cookie_domain(&self) -> &'static str {
+        "youtube.com"
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            videos: true,
+            live: false,
+            download: false,
+        }
+    }
+
+    // 订阅源只包含最新的 15 个视频，没有分页
+    fn newest_videos(
+        &self,
+        id: &str,
+        cookies: Option<&str>,
+        offset: Option<&str>,
+        _max_pages: u32,
+    ) -> Result<(Vec<NewestVideo>, String), VpmError> {
+        let offset = parse_offset(offset)?;
+        let feed = throttle::request(self.name(), || {
+            let response = http::client(self.name())?
+                .get(FEED_API)
+                .header(COOKIE, cookies.unwrap_or(""))
+                .query(&[("channel_id", id)])
+                .send()?
+                .error_for_status()?;
+            let text = response.text()?;
+            if text.trim().is_empty() {
+                return Err(VpmError::EmptyResponse);
+            }
+            Ok(text)
+        })?;
+        let document = Document::parse(&feed)
+            .map_err(|err| VpmError::Parse(format!("invalid feed: {}", err)))?;
+
+        let mut videos = Vec::new();
+        let mut next_offset: u64 = 0;
+        for entry in document
+            .root_element()
+            .children()
+            .filter(|node| node.has_tag_name("entry"))
+        {
+            let id = child_text(entry, (YT_NS, "videoId"))
+                .ok_or_else(|| VpmError::Parse("missing field `yt:videoId`".to_string()))?;
+            let title = child_text(entry, "title").unwrap_or_default();
+            let published = child_text(entry, "published")
+                .ok_or_else(|| VpmError::Parse(format!("missing field `published` of {}", id)))?;
+            let date = chrono::DateTime::parse_from_rfc3339(published)
+                .map_err(|err| VpmError::Parse(format!("invalid date {}: {}", published, err)))?
+                .timestamp_millis() as u64;
+            let url = entry
+                .children()
+                .find(|node| {
+                    node.has_tag_name("link") && node.attribute("rel") == Some("alternate")
+                })
+                .and_then(|node| node.attribute("href"))
+                .map(|href| href.to_string())
+                .unwrap_or_else(|| format!("{}watch?v={}", CHANNEL_PAGE, id));
+            // 订阅源只以链接区分 Shorts，没有标明首映
+            let kind = match url.contains("/shorts/") {
+                true => VideoKind::Short,
+                false => VideoKind::Video,
+            };
+            let video = NewestVideo {
+                id: id.to_string(),
+                title: title.to_string(),
+                url,
+                date: date.to_string(),
+                is_new: true,
+                kind,
+                raw: Value::Null,
+            };
+            collect_video(&mut videos, &mut next_offset, video, date, offset);
+        }
+
+        Ok((videos, next_offset.to_string()))
+    }
+}
+
+/// 频道 id 为 UC 开头的 24 位字符
+fn is_channel_id(id: &str) -> bool {
+    id.len() == 24
+        && id.starts_with("UC")
+        && id
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+}
+
+/// 第一个名称为 name 的子元素的文本
+fn child_text<'a, 'n, 'm>(
+    node: Node<'a, '_>,
+    name: impl Into<ExpandedName<'n, 'm>> + Copy,
+) -> Option<&'a str> {
+    node.children()
+        .find(|child| child.has_tag_name(name))
+        .and_then(|child| child.text())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::YouTube;
+    use crate::platform::Platform;
+    use url::Url;
+
+    // (链接, 频道 id)，@handle 等链接需要请求频道页面，不能直接解析
+    const LINKS: [(&str, Option<&str>); 7] = [
+        (
+            "https://www.youtube.com/channel/UCuAXFkgsw1L7xaCfnd5JJOw",
+            Some("UCuAXFkgsw1L7xaCfnd5JJOw"),
+        ),
+        (
+            "https://www.youtube.com/channel/UCuAXFkgsw1L7xaCfnd5JJOw/",
+            Some("UCuAXFkgsw1L7xaCfnd5JJOw"),
+        ),
+        (
+            "https://m.youtube.com/channel/UCuAXFkgsw1L7xaCfnd5JJOw/videos?view=0",
+            Some("UCuAXFkgsw1L7xaCfnd5JJOw"),
+        ),
+        ("https://www.youtube.com/channel/UCshort", None),
+        ("https://www.youtube.com/@handle", None),
+        ("https://www.youtube.com/user/name", None),
+        ("https://www.youtube.com/watch?v=dQw4w9WgXcQ", None),
+    ];
+
+    #[test]
+    fn user_id() {
+        for (link, id) in LINKS {
+            let url = Url::parse(link).unwrap();
+            assert_eq!(YouTube.user_id(&url).as_deref(), id, "{}", link);
+        }
+    }
+}
@@ -0,0 +1,146 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::mpsc;
+use std::sync::{Condvar, Mutex};
+use std::thread;
+
+/// 并发数限制
+#[derive(Debug, Clone, Copy)]
+pub struct Limits {
+    // 同时执行的任务总数
+    pub global: usize,
+    // 同一平台同时执行的任务数
+    pub platform: usize,
+}
+
+struct Queue {
+    pending: VecDeque<usize>,
+    // 平台 -> 正在执行的任务数
+    running: HashMap<String, usize>,
+}
+
+/// 正在执行的任务，结束时减少所属平台的任务数并唤醒等待的线程
+///
+/// 任务 panic 时同样会执行，否则等待该平台的线程会一直阻塞
+struct Running<'a> {
+    queue: &'a Mutex<Queue>,
+    available: &'a Condvar,
+    platform: &'a str,
+}
+
+impl Drop for Running<'_> {
+    fn drop(&mut self) {
+        let mut queue = self.queue.lock().unwrap_or_else(|err| err.into_inner());
+        if let Some(running) = queue.running.get_mut(self.platform) {
+            *running -= 1;
+        }
+        drop(queue);
+        self.available.notify_all();
+    }
+}
+
+/// 并发执行任务，并按输入顺序把结果交给 `emit`
+///
+/// 任务按顺序开始，同一平台的任务数超过限制时先执行其他平台的任务；`platforms[i]` 为第 i 个任务
+/// 所属的平台，由调用方事先确定，避免在持有队列锁时解析。
+/// `emit` 在调用线程中执行，前面的任务完成后才会收到后面任务的结果
+pub fn run_ordered<T, R>(
+    items: &[T],
+    platforms: &[String],
+    limits: Limits,
+    job: impl Fn(&T) -> R + Sync,
+    mut emit: impl FnMut(usize, R),
+) where
+    T: Sync,
+    R: Send,
+{
+    let queue = Mutex::new(Queue {
+        pending: (0..items.len()).collect(),
+        running: HashMap::new(),
+    });
+    let available = Condvar::new();
+    let (sender, receiver) = mpsc::channel();
+    let workers = limits.global.max(1).min(items.len());
+
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            let sender = sender.clone();
+            let (queue, available, job) = (&queue, &available, &job);
+            scope.spawn(move || loop {
+                // 取出第一个所属平台未达到并发上限的任务
+                let (index, platform) = {
+                    let mut queue = queue.lock().unwrap();
+                    loop {
+                        if queue.pending.is_empty() {
+                            return;
+                        }
+                        let next = queue.pending.iter().enumerate().find_map(|(pos, &index)| {
+                            let platform = &platforms[index];
+                            let running = queue.running.get(platform).copied().unwrap_or(0);
+                            (running < limits.platform.max(1)).then_some((pos, index, platform))
+                        });
+                        match next {
+                            Some((pos, index, platform)) => {
+                                queue.pending.remove(pos);
+                                *queue.running.entry(platform.clone()).or_default() += 1;
+                                break (index, platform);
+                            }
+                            None => queue = available.wait(queue).unwrap(),
+                        }
+                    }
+                };
+
+                let running = Running {
+                    queue,
+                    available,
+                    platform,
+                };
+                let result = job(&items[index]);
+                drop(running);
+                if sender.send((index, result)).is_err() {
+                    return;
+                }
+            });
+        }
+        drop(sender);
+
+        // 按输入顺序输出结果
+        let mut finished = BTreeMap::new();
+        let mut next = 0;
+        for (index, result) in receiver {
+            finished.insert(index, result);
+            while let Some(result) = finished.remove(&next) {
+                emit(next, result);
+                next += 1;
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{run_ordered, Limits};
+    use std::panic;
+
+    #[test]
+    fn job_panics() {
+        let limits = Limits {
+            global: 2,
+            platform: 1,
+        };
+        let platforms = vec!["bilibili".to_string(); 3];
+        // 第一个任务 panic 后，同一平台的其他任务仍会执行，不会一直等待
+        let result = panic::catch_unwind(|| {
+            run_ordered(
+                &[0, 1, 2],
+                &platforms,
+                limits,
+                |item| {
+                    assert_ne!(*item, 0, "job panicked");
+                    *item
+                },
+                |_, _| {},
+            )
+        });
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,593 @@
+use crate::config_helper::{Asset, Config};
+use crate::error::VpmError;
+use crate::platform;
+use chrono::{Datelike, FixedOffset, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, RwLock};
+use toml::de::Error;
+use toml::value::{Date, Datetime};
+
+// default state file name, placed next to the config file
+const DEFAULT_STATE_NAME: &str = "state.toml";
+// 每个资产最多记录的视频 id 数量
+const MAX_SEEN_IDS: usize = 1000;
+
+// 当前使用的状态文件，供保存平台级状态的模块使用
+static STATE_PATH: RwLock<Option<PathBuf>> = RwLock::new(None);
+// 并发检查时串行化状态文件的读写
+static STATE_LOCK: Mutex<()> = Mutex::new(());
+
+/// 运行时状态，与用户编辑的 config.toml 分开保存
+#[derive(Deserialize, Serialize, Clone, Debug, Default, PartialEq)]
+pub struct State {
+    // 键为资产 id
+    #[serde(default)]
+    pub assets: HashMap<String, AssetState>,
+    // 从浏览器导入的 cookies，键为平台名称，优先于配置文件中的 cookies 使用
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub cookies: HashMap<String, String>,
+    // 抖音游客 cookies
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub douyin: Option<DouyinGuest>,
+    // Bilibili wbi 签名使用的密钥
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wbi_keys: Option<WbiKeys>,
+    // 扫码登录 Bilibili 得到的 cookies
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bilibili_login: Option<BilibiliLogin>,
+}
+
+/// Bilibili 扫码登录的结果，优先于配置文件中的 cookies 使用
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct BilibiliLogin {
+    pub cookies: String,
+    // 刷新 cookies 时使用
+    pub refresh_token: String,
+}
+
+/// 抖音游客身份，未配置抖音 cookies 时使用
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct DouyinGuest {
+    // 从注册接口获取
+    pub ttwid: String,
+    // 以下在本地生成
+    pub ms_token: String,
+    pub verify_fp: String,
+}
+
+/// Bilibili wbi 签名密钥，每天北京时间零点后更换
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct WbiKeys {
+    pub img_key: String,
+    pub sub_key: String,
+    // 获取密钥的日期（北京时间）
+    pub date: Date,
+}
+
+impl WbiKeys {
+    pub fn new(img_key: String, sub_key: String) -> WbiKeys {
+        WbiKeys {
+            img_key,
+            sub_key,
+            date: naive_date_to_date(beijing_today()),
+        }
+    }
+
+    /// 密钥是否在北京时间今天获取
+    pub fn is_fresh(&self) -> bool {
+        self.date == naive_date_to_date(beijing_today())
+    }
+}
+
+fn beijing_today() -> NaiveDate {
+    let beijing = FixedOffset::east_opt(8 * 3600).expect("valid offset");
+    Utc::now().with_timezone(&beijing).date_naive()
+}
+
+/// 单个资产的运行时状态
+#[derive(Deserialize, Serialize, Clone, Debug, Default, PartialEq)]
+pub struct AssetState {
+    // 资产链接，便于人工查看
+    #[serde(default)]
+    pub link: String,
+    // 链接指向的作者，`平台:作者 id`，修改资产 id 或链接后据此迁移状态
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    // 最大长度为 3
+    // 分别记录 今日、上次、上上次 的偏移量
+    #[serde(default)]
+    pub offsets: Vec<Offset>,
+    // 上次检查时是否正在直播
+    #[serde(default)]
+    pub living: bool,
+    // 已见过的视频 id，按时间先后排列
+    #[serde(default)]
+    pub seen: Vec<String>,
+}
+
+impl AssetState {
+    /// 视频是否为新视频
+    ///
+    /// 记录过视频 id 时以 id 判断，否则退回到与偏移量比较发布时间
+    pub fn is_new(&self, id: &str, date: &str) -> bool {
+        if !self.seen.is_empty() {
+            return !self.seen.iter().any(|seen| seen == id);
+        }
+        let date = date.parse::<u64>().unwrap_or(0);
+        match self.offsets.get_newest_offset(true) {
+            Some(offset) => date > offset.mark.parse::<u64>().unwrap_or(0),
+            None => true,
+        }
+    }
+
+    /// 记录见过的视频 id，超出上限时丢弃最早的记录
+    pub fn remember<'a>(&mut self, ids: impl IntoIterator<Item = &'a str>) {
+        for id in ids {
+            if !self.seen.iter().any(|seen| seen == id) {
+                self.seen.push(id.to_string());
+            }
+        }
+        if self.seen.len() > MAX_SEEN_IDS {
+            let overflow = self.seen.len() - MAX_SEEN_IDS;
+            self.seen.drain(..overflow);
+        }
+    }
+}
+
+/// 一次检查对资产状态的修改，一轮检查结束后一起写入状态文件
+#[derive(Debug, Default)]
+pub struct AssetUpdate {
+    // 最新的偏移量
+    pub offset: Option<String>,
+    // 新见过的视频 id
+    pub seen: Vec<String>,
+    // 变化后的直播状态
+    pub living: Option<bool>,
+}
+
+impl AssetUpdate {
+    pub fn is_empty(&self) -> bool {
+        self.offset.is_none() && self.seen.is_empty() && self.living.is_none()
+    }
+
+    pub fn apply(&self, state: &mut AssetState) {
+        if let Some(offset) = &self.offset {
+            update_offset(state, offset);
+        }
+        state.remember(self.seen.iter().map(String::as_str));
+        if let Some(living) = self.living {
+            state.living = living;
+        }
+    }
+}
+
+impl State {
+    pub fn get(&self, asset: &Asset) -> Option<&AssetState> {
+        self.assets.get(&asset.get_id().ok()?)
+    }
+
+    pub fn get_mut(&mut self, asset: &Asset) -> Result<&mut AssetState, VpmError> {
+        let author = asset.author_key();
+        let state = self.assets.entry(asset.get_id()?).or_default();
+        state.link = asset.link.clone();
+        if author.is_some() {
+            state.author = author;
+        }
+        Ok(state)
+    }
+
+    /// 把记录的作者加入链接的解析缓存，短链接等不必在每次加载时重新请求平台
+    pub fn remember_authors(&self) {
+        for state in self.assets.values() {
+            if let Some(author) = &state.author {
+                platform::remember(&state.link, author);
+            }
+        }
+    }
+
+    /// 迁移旧版本的状态，返回是否有迁移
+    ///
+    /// - 以旧版本标识保存的状态，以及修改资产 id 或链接后遗留的、指向同一作者的状态，迁移到资产当前的标识。
+    ///   遗留状态按记录的作者判断，旧版本没有记录作者时只在本地解析其链接，不为此请求平台
+    /// - 旧版本保存在 config.toml 中的偏移量迁移到状态文件
+    pub fn migrate(&mut self, config: &Config) -> bool {
+        let assets = config.assets.iter().flatten().collect::<Vec<_>>();
+        let ids = assets
+            .iter()
+            .filter_map(|asset| asset.get_id().ok())
+            .collect::<HashSet<_>>();
+        let mut migrated = false;
+        for asset in &assets {
+            // 无法解析的资产等下次加载时再迁移
+            let Ok(id) = asset.get_id() else {
+                continue;
+            };
+            if self.assets.contains_key(&id) {
+                continue;
+            }
+            // 优先使用该资产旧版本标识的状态，其次是指向同一作者的遗留状态
+            let legacy_id = Some(asset.legacy_id()).filter(|key| self.assets.contains_key(key));
+            let orphan = legacy_id.or_else(|| {
+                let author = asset.author_key()?;
+                self.assets
+                    .iter()
+                    .filter(|(key, _)| !ids.contains(*key))
+                    .find(|(key, state)| {
+                        let state_author = state.author.clone().or_else(|| {
+                            platform::resolve_local(&state.link).map(|other| other.key())
+                        });
+                        **key == author || state_author.as_ref() == Some(&author)
+                    })
+                    .map(|(key, _)| key.clone())
+            });
+            if let Some(state) = orphan.and_then(|key| self.assets.remove(&key)) {
+                self.assets.insert(id, state);
+                let _ = self.get_mut(asset);
+                migrated = true;
+            }
+        }
+
+        for asset in assets {
+            let offsets = match &asset.offsets {
+                Some(offsets) if !offsets.is_empty() => offsets,
+                _ => continue,
+            };
+            let Ok(id) = asset.get_id() else {
+                continue;
+            };
+            if self.assets.contains_key(&id) {
+                continue;
+            }
+            let Ok(state) = self.get_mut(asset) else {
+                continue;
+            };
+            state.offsets = offsets.clone();
+            state.living = asset.living.unwrap_or(false);
+            migrated = true;
+        }
+        migrated
+    }
+}
+
+/// 状态文件路径：与配置文件位于同一目录
+pub fn state_path(config_path: &str) -> PathBuf {
+    Path::new(config_path).with_file_name(DEFAULT_STATE_NAME)
+}
+
+/// 读取状态文件，文件不存在时返回空状态
+///
+/// 其他读取错误不能当作空状态处理，否则写回时会清空已保存的偏移量
+pub fn read_state(path: &Path) -> Result<State, String> {
+    match std::fs::read_to_string(path) {
+        Ok(state) => toml::from_str(&state)
+            .map_err(|err: Error| format!("invalid state file {}: {}", path.display(), err)),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(State::default()),
+        Err(err) => Err(format!(
+            "failed to read state file {}: {}",
+            path.display(),
+            err
+        )),
+    }
+}
+
+/// 先写入临时文件再重命名，避免写入中断导致状态文件损坏
+pub fn save_state(state: &State, path: &Path) -> Result<(), std::io::Error> {
+    let content =
+        toml::to_string(state).map_err(|err| std::io::Error::new(ErrorKind::InvalidData, err))?;
+    let temp_path = PathBuf::from(format!("{}.tmp", path.display()));
+    std::fs::write(&temp_path, content)?;
+    std::fs::rename(&temp_path, path)
+}
+
+/// 设置当前使用的状态文件
+pub fn set_path(path: &Path) {
+    *STATE_PATH.write().unwrap() = Some(path.to_path_buf());
+}
+
+/// 读取当前使用的状态文件，未设置时返回 None
+pub fn read_current() -> Option<State> {
+    let path = STATE_PATH.read().unwrap().clone()?;
+    read_state(&path).ok()
+}
+
+/// 修改状态并写回状态文件，状态没有变化时不写入
+///
+/// 写入前重新读取状态，避免覆盖其他进程对状态文件的修改
+pub fn update(path: &Path, update: impl FnOnce(&mut State)) -> Result<(), String> {
+    let _lock = STATE_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+    let mut state = read_state(path)?;
+    let original = state.clone();
+    update(&mut state);
+    if state == original {
+        return Ok(());
+    }
+    save_state(&state, path).map_err(|err| format!("failed to save state: {}", err))
+}
+
+/// 修改当前使用的状态文件，未设置时不做任何事
+pub fn update_current(f: impl FnOnce(&mut State)) -> Result<(), String> {
+    let path = STATE_PATH.read().unwrap().clone();
+    match path {
+        Some(path) => update(&path, f),
+        None => Ok(()),
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct Offset {
+    pub date: Datetime,
+    pub mark: String,
+}
+pub trait OffsetVec {
+    fn get_newest_offset(&self, not_today: bool) -> Option<&Offset>;
+    fn get_oldest_offset(&self, not_today: bool) -> Option<&Offset>;
+    fn remove_newest_offset(&mut self);
+    fn remove_oldest_offset(&mut self);
+}
+impl OffsetVec for Vec<Offset> {
+    fn get_newest_offset(&self, not_today: bool) -> Option<&Offset> {
+        if self.is_empty() {
+            return None;
+        }
+
+        // 对比日期，返回最新的日期
+        let mut newest: Option<&Offset> = None;
+        for offset in self {
+            if not_today {
+                let today = chrono::Local::now().date_naive();
+                let today = naive_date_to_date(today);
+                if offset.date.date.unwrap() == today {
+                    continue;
+                }
+            }
+            if newest.is_none() || offset.date > newest.unwrap().date {
+                newest = Some(offset);
+            }
+        }
+        newest
+    }
+
+    fn get_oldest_offset(&self, not_today: bool) -> Option<&Offset> {
+        if self.is_empty() {
+            return None;
+        }
+
+        // 对比日期，返回最旧的日期
+        let mut oldest: Option<&Offset> = None;
+        for offset in self {
+            if not_today {
+                let today = chrono::Local::now().date_naive();
+                let today = naive_date_to_date(today);
+                if offset.date.date.unwrap() == today {
+                    continue;
+                }
+            }
+            if oldest.is_none() || offset.date < oldest.unwrap().date {
+                oldest = Some(offset);
+            }
+        }
+        oldest
+    }
+
+    fn remove_newest_offset(&mut self) {
+        // 对比日期，删除最新的日期
+        let date = {
+            let newest = self.get_newest_offset(false);
+            match newest {
+                None => return,
+                Some(offset) => offset.date.date,
+            }
+        };
+        self.retain(|offset| offset.date.date != date);
+    }
+
+    fn remove_oldest_offset(&mut self) {
+        // 对比日期，删除最旧的日期
+        let date = {
+            let oldest = self.get_oldest_offset(false);
+            match oldest {
+                None => return,
+                Some(offset) => offset.date.date,
+            }
+        };
+        self.retain(|offset| offset.date.date != date);
+    }
+}
+
+pub fn update_offset(asset: &mut AssetState, offset_mark: &str) {
+    let offset = &mut asset.offsets;
+
+    let today = chrono::Local::now().date_naive();
+    let today = Datetime::from(naive_date_to_date(today));
+
+    if let Some(newest_offset) = offset.get_newest_offset(false) {
+        if newest_offset.mark == offset_mark {
+            return;
+        }
+
+        if newest_offset.date == today {
+            offset.remove_newest_offset();
+        }
+        offset.push(Offset {
+            date: today,
+            mark: offset_mark.to_string(),
+        });
+    } else {
+        offset.push(Offset {
+            date: today,
+            mark: offset_mark.to_string(),
+        });
+    }
+
+    if offset.len() > 3 {
+        offset.remove_oldest_offset();
+    }
+}
+
+fn naive_date_to_date(date: NaiveDate) -> Date {
+    Date {
+        year: date.year() as u16,
+        month: date.month() as u8,
+        day: date.day() as u8,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AssetState, State};
+    use crate::config_helper::{Asset, Config};
+
+    fn config(assets: &str) -> Config {
+        toml::from_str(assets).unwrap()
+    }
+
+    fn asset_state(link: &str, author: Option<&str>, seen: &str) -> AssetState {
+        AssetState {
+            link: link.to_string(),
+            author: author.map(|author| author.to_string()),
+            seen: vec![seen.to_string()],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn migrate_legacy_id() {
+        let config = config(
+            r#"
+            [[assets]]
+            name = "up"
+            link = "https://space.bilibili.com/1344420936"
+            "#,
+        );
+        let legacy_id = config.assets.as_ref().unwrap()[0].legacy_id();
+        let mut state = State::default();
+        let link = "https://space.bilibili.com/1344420936";
+        state.assets.insert(legacy_id, asset_state(link, None, "a"));
+
+        assert!(state.migrate(&config));
+        let migrated = &state.assets["bilibili:1344420936"];
+        assert_eq!(migrated.seen, ["a"]);
+        assert_eq!(migrated.author.as_deref(), Some("bilibili:1344420936"));
+        assert_eq!(state.assets.len(), 1);
+        // 再次加载时没有需要迁移的状态
+        assert!(!state.migrate(&config));
+    }
+
+    #[test]
+    fn migrate_renamed_asset() {
+        // 旧版本以 名称 + 链接 为标识，改名后遗留的状态按链接指向的作者迁移
+        let config = config(
+            r#"
+            [[assets]]
+            name = "renamed"
+            link = "https://m.bilibili.com/space/1344420936?x=1"
+            "#,
+        );
+        let old = Asset {
+            name: Some("old".to_string()),
+            link: "https://space.bilibili.com/1344420936".to_string(),
+            ..Default::default()
+        };
+        let mut state = State::default();
+        state
+            .assets
+            .insert(old.legacy_id(), asset_state(&old.link, None, "a"));
+
+        assert!(state.migrate(&config));
+        assert_eq!(state.assets["bilibili:1344420936"].seen, ["a"]);
+        assert_eq!(state.assets.len(), 1);
+    }
+
+    #[test]
+    fn migrate_explicit_id() {
+        let config = config(
+            r#"
+            [[assets]]
+            id = "mine"
+            link = "https://space.bilibili.com/1344420936"
+
+            [[assets]]
+            id = "renamed"
+            link = "https://www.kuaishou.com/profile/3xxcvi49q2r52gu"
+            "#,
+        );
+        let mut state = State::default();
+        // 添加 id 之前以默认标识保存的状态
+        let link = "https://space.bilibili.com/1344420936";
+        state.assets.insert(
+            "bilibili:1344420936".to_string(),
+            asset_state(link, None, "a"),
+        );
+        // 修改 id 之前的状态，链接为短链接时按记录的作者迁移，不请求平台
+        state.assets.insert(
+            "kuai".to_string(),
+            asset_state(
+                "https://v.kuaishou.com/abc",
+                Some("kuaishou:3xxcvi49q2r52gu"),
+                "k",
+            ),
+        );
+
+        assert!(state.migrate(&config));
+        assert_eq!(state.assets["mine"].seen, ["a"]);
+        assert_eq!(state.assets["renamed"].seen, ["k"]);
+        assert_eq!(state.assets.len(), 2);
+    }
+
+    #[test]
+    fn remembered_author() {
+        let mut state = State::default();
+        let link = "https://v.kuaishou.com/remembered";
+        let author = Some("kuaishou:3xxcvi49q2r52gu");
+        state
+            .assets
+            .insert("kuai".to_string(), asset_state(link, author, "k"));
+        state.remember_authors();
+
+        // 记录过作者的短链接不再请求平台
+        let asset = Asset {
+            link: link.to_string(),
+            ..Default::default()
+        };
+        assert_eq!(asset.get_id().unwrap(), "kuaishou:3xxcvi49q2r52gu");
+        // 无法解析的链接没有标识，不会退回到旧版本的标识
+        let asset = Asset {
+            link: "https://example.com/user/1".to_string(),
+            ..Default::default()
+        };
+        assert!(asset.get_id().is_err());
+    }
+
+    #[test]
+    fn migrate_duplicate_links() {
+        // 两个链接指向同一作者，只迁移到一个标识，另一个遗留状态保持不变
+        let config = config(
+            r#"
+            [[assets]]
+            name = "first"
+            link = "https://space.bilibili.com/1344420936"
+
+            [[assets]]
+            name = "second"
+            link = "https://m.bilibili.com/space/1344420936"
+            "#,
+        );
+        let assets = config.assets.clone().unwrap();
+        let mut state = State::default();
+        for (asset, seen) in assets.iter().zip(["a", "b"]) {
+            state
+                .assets
+                .insert(asset.legacy_id(), asset_state(&asset.link, None, seen));
+        }
+
+        assert!(state.migrate(&config));
+        assert_eq!(config.duplicate_assets(), [(1, 0)]);
+        assert_eq!(state.assets.len(), 2);
+        assert_eq!(state.assets["bilibili:1344420936"].seen, ["a"]);
+        assert!(state.assets.contains_key(&assets[1].legacy_id()));
+        assert!(!state.migrate(&config));
+    }
+}
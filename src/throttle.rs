@@ -0,0 +1,151 @@
+use crate::config_helper::RateLimitConfig;
+use crate::error::VpmError;
+use crate::platform;
+use rand::{thread_rng, Rng};
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+// default requests per minute for each platform
+const DEFAULT_RATE: f64 = 30.0;
+// default number of requests that can be sent in a burst
+const DEFAULT_BURST: u32 = 5;
+// default number of retries for transient failures
+const DEFAULT_RETRIES: u32 = 3;
+// default cooldown in seconds after risk control is triggered
+const DEFAULT_COOLDOWN: u64 = 600;
+// backoff of the first retry, doubled on every retry
+const BASE_BACKOFF: Duration = Duration::from_secs(2);
+// upper bound of a single backoff
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// 单个平台的令牌桶
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+    // 触发风控后暂停请求到该时间
+    cooldown_until: Option<Instant>,
+}
+
+// 限流设置，未设置时使用默认值
+static SETTINGS: Mutex<Option<RateLimitConfig>> = Mutex::new(None);
+// 各平台的令牌桶
+static BUCKETS: OnceLock<Mutex<HashMap<&'static str, Bucket>>> = OnceLock::new();
+
+fn buckets() -> &'static Mutex<HashMap<&'static str, Bucket>> {
+    BUCKETS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// 更新限流设置，已有的令牌桶和冷却状态会保留
+pub fn configure(config: Option<&RateLimitConfig>) {
+    *SETTINGS.lock().unwrap() = config.cloned();
+}
+
+/// (每分钟请求数, 突发请求数)
+///
+/// 每分钟请求数：配置中的平台设置 > 配置中的全局设置 > 平台默认值
+fn rate_of(platform: &str) -> (f64, f64) {
+    let settings = SETTINGS.lock().unwrap();
+    let settings = settings.as_ref();
+    let rate = settings
+        .and_then(|s| s.platforms.as_ref())
+        .and_then(|p| p.get(platform).copied())
+        .or_else(|| settings.and_then(|s| s.rate))
+        .or_else(|| platform::get(platform).and_then(|p| p.defaults().rate))
+        .unwrap_or(DEFAULT_RATE);
+    let burst = settings.and_then(|s| s.burst).unwrap_or(DEFAULT_BURST);
+    (rate.max(0.1), f64::from(burst.max(1)))
+}
+
+fn retries() -> u32 {
+    let settings = SETTINGS.lock().unwrap();
+    settings
+        .as_ref()
+        .and_then(|s| s.retries)
+        .unwrap_or(DEFAULT_RETRIES)
+}
+
+fn cooldown() -> Duration {
+    let settings = SETTINGS.lock().unwrap();
+    Duration::from_secs(
+        settings
+            .as_ref()
+            .and_then(|s| s.cooldown)
+            .unwrap_or(DEFAULT_COOLDOWN),
+    )
+}
+
+/// 取得一个请求令牌，令牌不足时等待；平台处于风控冷却中时直接返回错误
+fn acquire(platform: &'static str) -> Result<(), VpmError> {
+    let (rate, burst) = rate_of(platform);
+    let wait = {
+        let mut buckets = buckets().lock().unwrap();
+        let now = Instant::now();
+        let bucket = buckets.entry(platform).or_insert(Bucket {
+            tokens: burst,
+            updated: now,
+            cooldown_until: None,
+        });
+
+        if let Some(until) = bucket.cooldown_until.filter(|until| *until > now) {
+            return Err(VpmError::Cooldown {
+                platform,
+                remaining: (until - now).as_secs() + 1,
+            });
+        }
+
+        // 按经过的时间补充令牌，令牌数可以为负，表示已被排队的请求预定
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * rate / 60.0).min(burst) - 1.0;
+        bucket.updated = now;
+        if bucket.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-bucket.tokens * 60.0 / rate)
+        }
+    };
+    std::thread::sleep(wait);
+    Ok(())
+}
+
+/// 让平台进入风控冷却
+fn start_cooldown(platform: &'static str) {
+    let until = Instant::now() + cooldown();
+    if let Some(bucket) = buckets().lock().unwrap().get_mut(platform) {
+        bucket.cooldown_until = Some(until);
+    }
+}
+
+/// 第 attempt 次重试前的等待时间：指数退避加随机抖动
+fn backoff(attempt: u32) -> Duration {
+    let backoff = BASE_BACKOFF
+        .saturating_mul(2u32.saturating_pow(attempt))
+        .min(MAX_BACKOFF);
+    backoff + backoff.mul_f64(thread_rng().gen_range(0.0..0.5))
+}
+
+/// 限流后发送请求，暂时性错误按指数退避重试，触发风控时让该平台进入冷却
+///
+/// `send` 每次调用都需要重新构造请求，并在其中检查平台返回的错误码
+pub fn request<T>(
+    platform: &'static str,
+    mut send: impl FnMut() -> Result<T, VpmError>,
+) -> Result<T, VpmError> {
+    let retries = retries();
+    let mut attempt = 0;
+    loop {
+        acquire(platform)?;
+        match send() {
+            Ok(value) => return Ok(value),
+            Err(err @ VpmError::RiskControl { .. }) => {
+                start_cooldown(platform);
+                return Err(err);
+            }
+            Err(err) if err.is_transient() && attempt < retries => {
+                std::thread::sleep(backoff(attempt));
+                attempt += 1;
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
@@ -0,0 +1,145 @@
+use base64::Engine;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// X-Bogus 使用的 base64 字符表
+const CHARACTER: &[u8; 64] = b"Dkdpgh4ZKsQB80/Mfvw36XI1R25-WUAlEi7NLboqYTOPuzmFjJnryx9HVGcaStCe";
+// 加密 User-Agent 使用的 RC4 密钥，与签名参数的第 2~4 位相同
+const UA_KEY: [u8; 3] = [0, 0, 0];
+// canvas 指纹，原脚本在非浏览器环境中取不到时为 0
+const CANVAS: u32 = 0;
+
+/// 计算请求参数的 X-Bogus 签名
+pub fn sign(query: &str, user_agent: &str) -> String {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|t| t.as_secs())
+        .unwrap_or_default();
+    sign_at(query, user_agent, timestamp as u32)
+}
+
+/// 按指定的时间戳（秒）计算 X-Bogus 签名
+pub fn sign_at(query: &str, user_agent: &str, timestamp: u32) -> String {
+    // User-Agent 经 RC4 加密、base64 编码后取 md5，每个 UTF-16 字符只取低 8 位
+    let ua = user_agent
+        .encode_utf16()
+        .map(|c| c as u8)
+        .collect::<Vec<u8>>();
+    let ua = base64::engine::general_purpose::STANDARD.encode(rc4(&UA_KEY, &ua));
+    let ua_hash = md5::compute(ua.as_bytes()).0;
+    // 请求参数和请求体（GET 请求为空）各取两次 md5
+    let query_hash = double_md5(query.as_bytes());
+    let body_hash = double_md5(b"");
+
+    let mut values = vec![64u8];
+    values.extend(UA_KEY);
+    values.extend([query_hash[14], query_hash[15]]);
+    values.extend([body_hash[14], body_hash[15]]);
+    values.extend([ua_hash[14], ua_hash[15]]);
+    values.extend(timestamp.to_be_bytes());
+    values.extend(CANVAS.to_be_bytes());
+    values.push(values.iter().fold(0, |acc, v| acc ^ v));
+
+    // 偶数位在前、奇数位在后，再按固定顺序重排
+    let merged = values
+        .iter()
+        .step_by(2)
+        .chain(values.iter().skip(1).step_by(2))
+        .copied()
+        .collect::<Vec<u8>>();
+    const ORDER: [usize; 19] = [
+        0, 10, 1, 11, 2, 12, 3, 13, 4, 14, 5, 15, 6, 16, 7, 17, 8, 18, 9,
+    ];
+    let shuffled = ORDER.iter().map(|&i| merged[i]).collect::<Vec<u8>>();
+
+    let mut garbled = vec![2u8, 255];
+    garbled.extend(rc4(&[255], &shuffled));
+
+    garbled
+        .chunks(3)
+        .flat_map(|chunk| {
+            let n = (u32::from(chunk[0]) << 16) | (u32::from(chunk[1]) << 8) | u32::from(chunk[2]);
+            [18, 12, 6, 0].map(|shift| CHARACTER[((n >> shift) & 63) as usize] as char)
+        })
+        .collect()
+}
+
+fn double_md5(data: &[u8]) -> [u8; 16] {
+    md5::compute(md5::compute(data).0).0
+}
+
+/// RC4 加密，a_bogus 同样使用
+pub(crate) fn rc4(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut s: Vec<u8> = (0..=255).collect();
+    let mut j = 0usize;
+    for i in 0..256 {
+        j = (j + s[i] as usize + key[i % key.len()] as usize) % 256;
+        s.swap(i, j);
+    }
+
+    let (mut i, mut j) = (0usize, 0usize);
+    data.iter()
+        .map(|byte| {
+            i = (i + 1) % 256;
+            j = (j + s[i] as usize) % 256;
+            s.swap(i, j);
+            byte ^ s[(s[i] as usize + s[j] as usize) % 256]
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::sign_at;
+    use crate::http::DEFAULT_USER_AGENT;
+
+    const MAC_USER_AGENT: &str = "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36";
+
+    // (query, user agent, timestamp, X-Bogus)，由 x_bogus_js 在固定 Date.now 下生成
+    const VECTORS: [(&str, &str, u32, &str); 6] = [
+        (
+            "aid=6383&sec_user_id=MS4wLjABAAAA&count=10&max_cursor=0&cookie_enabled=true&platform=PC&downlink=10",
+            DEFAULT_USER_AGENT,
+            1700000000,
+            "DFSzswSLik0ANVB-tmWx-t9WcBnw",
+        ),
+        (
+            "aid=6383&sec_user_id=MS4wLjABAAAAabc&count=10&max_cursor=1712345678000&cookie_enabled=true&platform=PC&downlink=10",
+            MAC_USER_AGENT,
+            1712345678,
+            "DFSzswSLK12ANcpTt5wIKU9WcBrF",
+        ),
+        ("a=1", "ua", 1, "DFSzswSLC0kANSqdLa3g-z9WcBnG"),
+        (
+            "keyword=%E4%B8%AD%E6%96%87&offset=20",
+            "curl/8.0",
+            1234567890,
+            "DFSzswSLnDiANHKT97B4FU9WcBjM",
+        ),
+        ("keyword=中文", "ua 中", 1234567890, "DFSzswSLn4xANcnG97B4FU9WcBjL"),
+        ("", DEFAULT_USER_AGENT, 1800000000, "DFSzswSL0IJANVB-CMyI-t9WcBJP"),
+    ];
+
+    #[test]
+    fn matches_vectors() {
+        for (query, user_agent, timestamp, expected) in VECTORS {
+            assert_eq!(sign_at(query, user_agent, timestamp), expected, "{}", query);
+        }
+    }
+
+    #[cfg(feature = "quickjs")]
+    #[test]
+    fn matches_js() {
+        for (query, user_agent, timestamp, _) in VECTORS {
+            let context = quick_js::Context::new().unwrap();
+            let script = format!(
+                "{}\n;Date.now = () => {t}000;\nDate.prototype.getTime = () => {t}000;\nsign(`{}`, `{}`);",
+                crate::x_bogus_js::X_BOGUS_JS,
+                query,
+                user_agent,
+                t = timestamp
+            );
+            let expected = context.eval_as::<String>(&script).unwrap();
+            assert_eq!(sign_at(query, user_agent, timestamp), expected, "{}", query);
+        }
+    }
+}